@@ -1,9 +1,19 @@
+use crate::cache::{Cache, CachedArchive};
 use crate::errors::{Error, Result};
-use crate::htmlparser;
-
+use crate::extractor::{self, ExtractLimits};
+use crate::htmlparser::{self, ArchivedFile, ChangelogEntry};
+use crate::http::{EsouiClient, HttpClient, ESOUI_URL};
+use crate::lock::LockedAddon;
+use crate::manifest::{self, Dependency, Manifest};
+use crate::progress::{ProgressWriter, Transfer};
+
+use md5::Md5;
 use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
 use std::fs::{self, File};
-use std::io::{self, BufRead};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use tempfile::tempfile;
 use walkdir::WalkDir;
@@ -11,7 +21,74 @@ use walkdir::WalkDir;
 #[derive(Debug)]
 pub struct Addon {
     pub name: String,
-    pub depends_on: Vec<String>,
+    pub path: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Addon {
+    pub fn depends_on(&self) -> &[Dependency] {
+        &self.manifest.depends_on
+    }
+
+    pub fn optional_depends_on(&self) -> &[Dependency] {
+        &self.manifest.optional_depends_on
+    }
+}
+
+/// An addon installed from esoui.com, together with the data needed to
+/// install the same archive again.
+#[derive(Debug)]
+pub struct Download {
+    /// The addon the esoui.com entry is named after.
+    pub addon: Addon,
+    /// Other addons shipped next to it in the same archive, e.g. libraries.
+    pub bundled: Vec<Addon>,
+    pub file_id: Option<u32>,
+    pub version: Option<String>,
+    pub updated: Option<String>,
+    pub download_url: String,
+    pub sha256: String,
+    pub folders: Vec<String>,
+    /// Files of the previous version, which are not part of the new one,
+    /// relative to the addon directory.
+    pub removed_files: Vec<PathBuf>,
+}
+
+/// The latest release of an addon, as shown on its esoui.com page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteAddon {
+    pub url: String,
+    pub file_id: Option<u32>,
+    pub version: Option<String>,
+    pub updated: Option<String>,
+    pub download_link: Option<String>,
+    /// MD5 of the latest archive, as published on esoui.com.
+    pub md5: Option<String>,
+    pub compatibility: Option<String>,
+    pub description: Option<String>,
+    /// Changelog from the file page, newest release first.
+    pub changelog: Vec<ChangelogEntry>,
+    /// Earlier releases listed on the same page.
+    pub archived_files: Vec<ArchivedFile>,
+}
+
+/// A downloaded and verified archive, which isn't installed yet.
+pub struct FetchedArchive {
+    file: File,
+    url: String,
+    file_id: Option<u32>,
+    version: Option<String>,
+    updated: Option<String>,
+    download_url: String,
+    sha256: String,
+    from_cache: bool,
+}
+
+struct Extracted {
+    addon: Addon,
+    bundled: Vec<Addon>,
+    folders: Vec<String>,
+    removed_files: Vec<PathBuf>,
 }
 
 pub struct AddonList {
@@ -19,20 +96,66 @@ pub struct AddonList {
     pub errors: Vec<Error>,
 }
 
+// Temporary directories are created inside the addon directory, so that
+// moving folders in and out of them is a rename on the same filesystem.
+const STAGING_PREFIX: &str = ".eso-addons-staging-";
+const BACKUP_PREFIX: &str = ".eso-addons-backup-";
+
 pub struct Manager {
     addon_dir: PathBuf,
-}
-
-fn extract_dependency(dep: &str) -> Option<String> {
-    let re = Regex::new(r"^(.+?)(([<=>]+)(.*))?$").unwrap();
-    re.captures(dep).map(|captures| captures[1].to_owned())
+    cache: Option<Cache>,
+    offline: bool,
+    client: Box<dyn EsouiClient>,
+    limits: ExtractLimits,
 }
 
 impl Manager {
     pub fn new(addon_dir: &Path) -> Manager {
         let path = PathBuf::from(addon_dir);
 
-        Manager { addon_dir: path }
+        Manager {
+            addon_dir: path,
+            cache: None,
+            offline: false,
+            client: Box::new(HttpClient::default()),
+            limits: ExtractLimits::default(),
+        }
+    }
+
+    pub fn with_extract_limits(mut self, limits: ExtractLimits) -> Manager {
+        self.limits = limits;
+        self
+    }
+
+    /// Accesses esoui.com through `client`, e.g. a configured [`HttpClient`].
+    pub fn with_client<C: EsouiClient + 'static>(mut self, client: C) -> Manager {
+        self.client = Box::new(client);
+        self
+    }
+
+    pub fn client(&self) -> &dyn EsouiClient {
+        self.client.as_ref()
+    }
+
+    /// Keeps downloaded archives in `cache` and installs them from there,
+    /// when the same release is installed again.
+    pub fn with_cache(mut self, cache: Cache) -> Manager {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// In offline mode archives are only installed from the cache.
+    pub fn with_offline(mut self, offline: bool) -> Manager {
+        self.offline = offline;
+        self
+    }
+
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
     }
 
     pub fn get_addons(&self) -> Result<AddonList> {
@@ -48,27 +171,25 @@ impl Manager {
             ));
         }
 
-        for entry in WalkDir::new(&self.addon_dir) {
+        let entries = WalkDir::new(&self.addon_dir)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|entry| !is_temporary_dir(entry.file_name()));
+
+        for entry in entries {
             let entry_dir = entry.map_err(|err| {
                 Error::CannotOpenAddonDirectory(self.addon_dir.clone(), Box::new(err))
             })?;
-            let file_path = entry_dir.path();
-
-            let file_name = entry_dir.file_name();
-            let parent_dir_name = file_path.parent().map(|f| f.file_name()).flatten();
-
-            match parent_dir_name {
-                None => continue,
-                Some(parent_dir_name) => {
-                    let mut name = parent_dir_name.to_os_string();
-                    name.push(".txt");
-                    if name != file_name {
-                        continue;
-                    }
-                }
+
+            if !entry_dir.file_type().is_dir() {
+                continue;
             }
 
-            let addon_dir = file_path.parent().unwrap();
+            let addon_dir = entry_dir.path();
+
+            if manifest::find_manifest_file(addon_dir).is_none() {
+                continue;
+            }
 
             match self.read_addon(addon_dir) {
                 Ok(addon) => addon_list.addons.push(addon),
@@ -89,131 +210,431 @@ impl Manager {
         let addon_name = path.file_name().unwrap().to_str().unwrap();
 
         let file = self
-            .open_addon_metadata_file(path, addon_name)
+            .open_addon_metadata_file(path)
+            .map_err(|err| Error::CannotReadAddon(addon_name.to_owned(), Box::new(err)))?;
+
+        let manifest = Manifest::parse(file)
             .map_err(|err| Error::CannotReadAddon(addon_name.to_owned(), Box::new(err)))?;
-        let re = Regex::new(r"## (.*): (.*)").unwrap();
 
-        let mut addon = Addon {
+        Ok(Addon {
             name: addon_name.to_owned(),
-            depends_on: vec![],
-        };
+            path: path.to_owned(),
+            manifest,
+        })
+    }
+
+    /// Deletes top-level folders, which a previous version of an addon
+    /// installed, but the current one doesn't. Returns the deleted files.
+    pub fn delete_folders(&self, folders: &[String]) -> Result<Vec<PathBuf>> {
+        let removed_files = list_files(&self.addon_dir, folders).into_iter().collect();
 
-        let lines = io::BufReader::new(file).lines();
-        for line in lines {
-            match line {
-                Ok(line) => {
-                    if line.starts_with("## DependsOn:") {
-                        let depends_on = match re.captures(&line) {
-                            Some(ref captures) => captures[2]
-                                .split(" ")
-                                .map(|s| s.to_owned())
-                                .into_iter()
-                                .filter_map(|s| extract_dependency(&s).to_owned())
-                                .collect(),
-                            None => vec![],
-                        };
-
-                        addon.depends_on = depends_on;
-                    }
-                }
-                _ => {}
+        for folder in folders {
+            let path = self.addon_dir.join(folder);
+            if path.exists() {
+                fs::remove_dir_all(path)
+                    .map_err(|err| Error::CannotRemoveAddon(folder.to_owned(), Box::new(err)))?;
             }
         }
 
-        Ok(addon)
+        Ok(removed_files)
     }
 
     pub fn delete_addon(&self, addon: &Addon) -> Result<()> {
-        let mut addon_path = self.addon_dir.to_owned();
-        addon_path.push(&addon.name);
-
-        fs::remove_dir_all(addon_path)
+        fs::remove_dir_all(&addon.path)
             .map_err(|err| Error::CannotRemoveAddon(addon.name.to_owned(), Box::new(err)))?;
         Ok(())
     }
 
-    pub fn download_addon(&self, url: &str) -> Result<Addon> {
-        let download_link = htmlparser::get_document(url).map(htmlparser::get_cdn_download_link)?;
-        let download_link = download_link.ok_or(Error::CannotDownloadAddon(
-            url.to_owned(),
-            "CDN link missing".into(),
-        ))?;
+    /// Downloads and installs the addon. If `version` is set, that version is
+    /// installed from the file history on esoui.com instead of the latest one.
+    pub fn download_addon(&self, url: &str, version: Option<&str>) -> Result<Download> {
+        let remote = self.get_remote_addon(url)?;
+        self.install_remote_addon(&remote, version)
+    }
 
-        let mut response = reqwest::blocking::get(&download_link)
-            .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
+    /// Fetches the details of the latest release without downloading it.
+    pub fn get_remote_addon(&self, url: &str) -> Result<RemoteAddon> {
+        if self.offline {
+            return Err(Error::Offline(url.to_owned()));
+        }
 
-        let mut tmpfile = tempfile()?;
-        response
-            .copy_to(&mut tmpfile)
-            .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
-        let mut archive = zip::ZipArchive::new(tmpfile)
-            .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
+        let document = htmlparser::get_document(self.client(), url)?;
+
+        Ok(RemoteAddon {
+            url: url.to_owned(),
+            file_id: get_file_id(self.client.base_url(), url),
+            version: htmlparser::get_addon_version(&document),
+            updated: htmlparser::get_addon_updated(&document),
+            download_link: htmlparser::get_cdn_download_link(&document, self.client.cdn_url()),
+            md5: htmlparser::get_addon_md5(&document),
+            compatibility: htmlparser::get_addon_compatibility(&document),
+            description: htmlparser::get_addon_description(&document),
+            changelog: htmlparser::get_changelog(&document),
+            archived_files: htmlparser::get_archived_files(&document, self.client.base_url()),
+        })
+    }
+
+    /// Installs the release fetched with [`Manager::get_remote_addon`], or the
+    /// pinned `version` from the file history.
+    pub fn install_remote_addon(
+        &self,
+        remote: &RemoteAddon,
+        version: Option<&str>,
+    ) -> Result<Download> {
+        let archive = self.fetch_remote_addon(remote, version, &|_| {})?;
+        self.install_archive(archive)
+    }
+
+    /// Installs the most recently cached archive of the addon, or the cached
+    /// archive of the pinned `version`, without accessing esoui.com.
+    pub fn install_cached_addon(
+        &self,
+        name: &str,
+        url: &str,
+        version: Option<&str>,
+    ) -> Result<Download> {
+        let archive = self.fetch_cached_addon(name, url, version)?;
+        self.install_archive(archive)
+    }
+
+    /// Installs exactly the archive recorded in the lock file. Fails, if the
+    /// archive changed since it was locked.
+    pub fn download_locked_addon(&self, locked: &LockedAddon) -> Result<Download> {
+        let archive = self.fetch_locked_addon(locked, &|_| {})?;
+        self.install_archive(archive)
+    }
 
-        for i in 0..archive.len() {
-            let mut file = archive
-                .by_index(i)
-                .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
-            let outpath = match file.enclosed_name() {
-                Some(path) => {
-                    let mut p = self.addon_dir.clone();
-                    p.push(path);
-                    p
-                }
-
-                None => continue,
-            };
-
-            if (file.name()).ends_with('/') {
-                fs::create_dir_all(&outpath)
-                    .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
-            } else {
-                if let Some(p) = outpath.parent() {
-                    if !p.exists() {
-                        fs::create_dir_all(&p).map_err(|err| {
-                            Error::CannotDownloadAddon(url.to_owned(), Box::new(err))
-                        })?;
-                    }
-                }
-                let mut outfile = fs::File::create(&outpath)
-                    .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
-                io::copy(&mut file, &mut outfile)
-                    .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
+    /// Downloads the archive of [`Manager::install_remote_addon`] without
+    /// installing it. Fetching doesn't touch the addon directory, so several
+    /// archives can be fetched at the same time. The download is reported to
+    /// `on_transfer`.
+    pub fn fetch_remote_addon(
+        &self,
+        remote: &RemoteAddon,
+        version: Option<&str>,
+        on_transfer: &dyn Fn(Transfer),
+    ) -> Result<FetchedArchive> {
+        let url = &remote.url;
+
+        let pinned = version.filter(|version| remote.version.as_deref() != Some(*version));
+        let (version, updated) = match pinned {
+            Some(version) => (Some(version.to_owned()), None),
+            None => (remote.version.clone(), remote.updated.clone()),
+        };
+
+        if let Some(cached) =
+            self.find_cached_archive(remote.file_id, version.as_deref(), updated.as_deref())?
+        {
+            return self.fetch_cached_archive(cached);
+        }
+
+        let download_link = match pinned {
+            Some(version) => self.get_archived_download_link(remote, version)?,
+            None => remote.download_link.clone().ok_or_else(|| {
+                Error::CannotDownloadAddon(url.to_owned(), "CDN link missing".into())
+            })?,
+        };
+
+        let (file, checksums) = self.fetch_archive(url, &download_link, on_transfer)?;
+
+        // the published checksum belongs to the latest release only
+        if let (None, Some(expected)) = (pinned, &remote.md5) {
+            if !checksums.md5.eq_ignore_ascii_case(expected) {
+                return Err(Error::PublishedChecksumMismatch {
+                    url: url.to_owned(),
+                    expected: expected.to_owned(),
+                    actual: checksums.md5,
+                });
             }
         }
+        let sha256 = checksums.sha256;
+
+        Ok(FetchedArchive {
+            file,
+            url: url.to_owned(),
+            file_id: remote.file_id,
+            version,
+            updated,
+            download_url: download_link,
+            sha256,
+            from_cache: false,
+        })
+    }
 
-        let mut addon_path = self.addon_dir.to_owned();
-        let addon_name = archive
-            .by_index(0)
-            .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
-        let addon_name = get_root_dir(&addon_name.mangled_name());
-        addon_path.push(addon_name);
+    /// Takes the archive of [`Manager::install_cached_addon`] from the cache.
+    pub fn fetch_cached_addon(
+        &self,
+        name: &str,
+        url: &str,
+        version: Option<&str>,
+    ) -> Result<FetchedArchive> {
+        let cached = match (&self.cache, get_file_id(self.client.base_url(), url)) {
+            (Some(cache), Some(file_id)) => cache.find(file_id, version)?,
+            _ => None,
+        };
 
-        let addon = self.read_addon(&addon_path)?;
+        match cached {
+            Some(cached) => self.fetch_cached_archive(cached),
+            None => Err(Error::NotCached(name.to_owned())),
+        }
+    }
+
+    /// Downloads and verifies the archive of [`Manager::download_locked_addon`].
+    pub fn fetch_locked_addon(
+        &self,
+        locked: &LockedAddon,
+        on_transfer: &dyn Fn(Transfer),
+    ) -> Result<FetchedArchive> {
+        let cached = match &self.cache {
+            Some(cache) => cache.get(&locked.sha256)?,
+            None => None,
+        };
+        let from_cache = cached.is_some();
+
+        let (file, sha256) = match cached {
+            Some(file) => (file, locked.sha256.to_owned()),
+            None if self.offline => return Err(Error::NotCached(locked.name.to_owned())),
+            None => self
+                .fetch_archive(&locked.url, &locked.url, on_transfer)
+                .map(|(file, checksums)| (file, checksums.sha256))?,
+        };
+
+        if sha256 != locked.sha256 {
+            return Err(Error::ChecksumMismatch {
+                name: locked.name.to_owned(),
+                expected: locked.sha256.to_owned(),
+                actual: sha256,
+            });
+        }
 
-        Ok(addon)
+        Ok(FetchedArchive {
+            file,
+            url: locked.url.to_owned(),
+            file_id: locked.file_id,
+            version: locked.version.clone(),
+            updated: locked.updated.clone(),
+            download_url: locked.url.to_owned(),
+            sha256,
+            from_cache,
+        })
     }
 
-    fn open_addon_metadata_file(&self, path: &Path, addon_name: &str) -> Result<File> {
-        let mut filepath = path.to_owned();
-        let mut filepath_lowercase = path.to_owned();
+    /// Extracts a fetched archive into the addon directory.
+    pub fn install_archive(&self, mut archive: FetchedArchive) -> Result<Download> {
+        let extracted = self.extract_archive(&archive.url, &archive.file)?;
+
+        if !archive.from_cache {
+            self.cache_archive(
+                &mut archive.file,
+                CachedArchive {
+                    name: extracted.addon.name.clone(),
+                    file_id: archive.file_id,
+                    version: archive.version.clone(),
+                    updated: archive.updated.clone(),
+                    url: archive.download_url.clone(),
+                    sha256: archive.sha256.clone(),
+                    size: 0,
+                },
+            );
+        }
 
-        let filename = PathBuf::from(format!("{}.txt", addon_name));
-        let filename_lowercase = PathBuf::from(format!("{}.txt", addon_name.to_lowercase()));
+        Ok(Download {
+            addon: extracted.addon,
+            bundled: extracted.bundled,
+            file_id: archive.file_id,
+            version: archive.version,
+            updated: archive.updated,
+            download_url: archive.download_url,
+            sha256: archive.sha256,
+            folders: extracted.folders,
+            removed_files: extracted.removed_files,
+        })
+    }
 
-        filepath.push(filename);
-        filepath_lowercase.push(filename_lowercase);
+    fn get_archived_download_link(&self, remote: &RemoteAddon, version: &str) -> Result<String> {
+        let url = &remote.url;
+        let find = |files: &[ArchivedFile]| {
+            files
+                .iter()
+                .find(|file| file.version == version)
+                .map(|file| file.url.clone())
+        };
+
+        // only the info page is sure to list the file history, it's fetched,
+        // if the page of the remote addon didn't
+        let link = if remote.archived_files.is_empty() {
+            let base_url = self.client.base_url();
+            let file_id = get_file_id(base_url, url).ok_or_else(|| {
+                Error::CannotDownloadAddon(url.to_owned(), "cannot find the esoui file ID".into())
+            })?;
+            let info_url = htmlparser::get_info_url(base_url, file_id);
+            let document = htmlparser::get_document(self.client(), &info_url)?;
 
-        if filepath.exists() {
-            File::open(&filepath).map_err(|err| Error::Other(Box::new(err)))
-        } else if filepath_lowercase.exists() {
-            File::open(&filepath_lowercase).map_err(|err| Error::Other(Box::new(err)))
+            find(&htmlparser::get_archived_files(&document, base_url))
         } else {
-            Err(Error::Other("missing addon metadata file".into()))
+            find(&remote.archived_files)
+        };
+
+        link.ok_or_else(|| Error::VersionNotFound(url.to_owned(), version.to_owned()))
+    }
+
+    fn fetch_cached_archive(&self, cached: CachedArchive) -> Result<FetchedArchive> {
+        self.fetch_locked_addon(
+            &LockedAddon {
+                name: cached.name,
+                file_id: cached.file_id,
+                version: cached.version,
+                updated: cached.updated,
+                url: cached.url,
+                sha256: cached.sha256,
+                folders: vec![],
+            },
+            &|_| {},
+        )
+    }
+
+    fn find_cached_archive(
+        &self,
+        file_id: Option<u32>,
+        version: Option<&str>,
+        updated: Option<&str>,
+    ) -> Result<Option<CachedArchive>> {
+        let (cache, file_id, version) = match (&self.cache, file_id, version) {
+            (Some(cache), Some(file_id), Some(version)) => (cache, file_id, version),
+            _ => return Ok(None),
+        };
+
+        // a release uploaded again under the same version has a new date
+        let cached = cache
+            .find(file_id, Some(version))?
+            .filter(|cached| updated.is_none() || cached.updated.as_deref() == updated);
+        Ok(cached)
+    }
+
+    // The cache only saves downloads, installing doesn't fail because of it.
+    fn cache_archive(&self, archive: &mut File, record: CachedArchive) {
+        if let Some(cache) = &self.cache {
+            let _ = cache.insert(archive, record);
+        }
+    }
+
+    fn fetch_archive(
+        &self,
+        url: &str,
+        download_link: &str,
+        on_transfer: &dyn Fn(Transfer),
+    ) -> Result<(File, Checksums)> {
+        if self.offline {
+            return Err(Error::Offline(download_link.to_owned()));
+        }
+
+        let mut fetched = None;
+        self.client
+            .download(download_link, &mut |body, total| {
+                let mut writer =
+                    ProgressWriter::new(HashWriter::new(tempfile()?), total, on_transfer);
+                io::copy(body, &mut writer)?;
+                fetched = Some(writer.finish().finish());
+                Ok(())
+            })
+            .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
+
+        fetched.ok_or_else(|| Error::CannotDownloadAddon(url.to_owned(), "no response".into()))
+    }
+
+    /// Extracts the archive into a staging directory next to the addons and
+    /// swaps the extracted folders in place of the installed ones. The
+    /// installed addon is left untouched, if anything fails.
+    fn extract_archive(&self, url: &str, archive: &File) -> Result<Extracted> {
+        fs::create_dir_all(&self.addon_dir)
+            .map_err(|err| Error::CannotInstallAddon(url.to_owned(), Box::new(err)))?;
+        let staging_dir = tempfile::Builder::new()
+            .prefix(STAGING_PREFIX)
+            .tempdir_in(&self.addon_dir)
+            .map_err(|err| Error::CannotInstallAddon(url.to_owned(), Box::new(err)))?;
+
+        let paths = extractor::extract(archive, staging_dir.path(), &self.limits)
+            .map_err(|err| Error::CannotExtractAddon(url.to_owned(), err))?;
+
+        let addon_name = paths
+            .first()
+            .map(|path| get_root_dir(path))
+            .ok_or_else(|| Error::CannotInstallAddon(url.to_owned(), "empty archive".into()))?;
+
+        // fail before touching the installed addon, if the archive is broken
+        self.read_addon(&staging_dir.path().join(&addon_name))?;
+
+        let mut folders: Vec<String> = fs::read_dir(staging_dir.path())
+            .map_err(|err| Error::CannotInstallAddon(url.to_owned(), Box::new(err)))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        folders.sort();
+
+        // every other folder with a manifest is an addon of its own
+        let mut bundled = vec![];
+        for folder in folders.iter().filter(|folder| **folder != addon_name) {
+            let path = staging_dir.path().join(folder);
+            if manifest::find_manifest_file(&path).is_some() {
+                self.read_addon(&path)?;
+                bundled.push(folder.clone());
+            }
+        }
+
+        let previous_files = list_files(&self.addon_dir, &folders);
+        let new_files = list_files(staging_dir.path(), &folders);
+        let removed_files = previous_files.difference(&new_files).cloned().collect();
+
+        replace_folders(&self.addon_dir, staging_dir.path(), &folders)
+            .map_err(|err| Error::CannotInstallAddon(url.to_owned(), Box::new(err)))?;
+
+        let addon = self.read_addon(&self.addon_dir.join(&addon_name))?;
+        let bundled = bundled
+            .iter()
+            .map(|folder| self.read_addon(&self.addon_dir.join(folder)))
+            .collect::<Result<Vec<Addon>>>()?;
+
+        Ok(Extracted {
+            addon,
+            bundled,
+            folders,
+            removed_files,
+        })
+    }
+
+    fn open_addon_metadata_file(&self, path: &Path) -> Result<File> {
+        match manifest::find_manifest_file(path) {
+            Some(filepath) => File::open(&filepath).map_err(|err| Error::Other(Box::new(err))),
+            None => Err(Error::Other("missing addon metadata file".into())),
         }
     }
 }
 
-pub fn get_download_url(addon_url: &str) -> Option<String> {
+/// Total size in bytes and number of the files below `path`.
+pub fn get_folder_size(path: &Path) -> (u64, usize) {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .fold((0, 0), |(size, count), metadata| {
+            (size + metadata.len(), count + 1)
+        })
+}
+
+/// Returns the esoui file ID of an addon URL on esoui.com or below `base_url`.
+pub fn get_file_id(base_url: &str, addon_url: &str) -> Option<u32> {
+    let addon_url = rebase_url(base_url, addon_url);
+    let re = Regex::new(r"esoui\.com/downloads/(?:info|download|fileinfo\.php\?id=)(\d+)").unwrap();
+    re.captures(&addon_url)
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+/// Returns the download page below `base_url` of an addon URL on esoui.com
+/// or below `base_url`.
+pub fn get_download_url(base_url: &str, addon_url: &str) -> Option<String> {
+    let addon_url = rebase_url(base_url, addon_url);
     let fns: Vec<fn(&str) -> Option<String>> = vec![
         |url: &str| {
             let re = Regex::new(r"^https://.*esoui\.com/downloads/info(\d+)-(.+)$").unwrap();
@@ -227,15 +648,128 @@ pub fn get_download_url(addon_url: &str) -> Option<String> {
     ];
 
     for f in fns {
-        let url = f(addon_url);
+        let url = f(&addon_url);
         if let Some(id) = url {
-            return Some(format!("https://www.esoui.com/downloads/download{}", id));
+            return Some(format!("{}downloads/download{}", base_url, id));
         }
     }
 
     None
 }
 
+// URLs below a configured base URL are parsed like esoui.com URLs.
+fn rebase_url(base_url: &str, addon_url: &str) -> String {
+    match addon_url.strip_prefix(base_url) {
+        Some(path) => format!("{}{}", ESOUI_URL, path),
+        None => addon_url.to_owned(),
+    }
+}
+
+/// Hex encoded checksums of a downloaded archive.
+struct Checksums {
+    sha256: String,
+    md5: String,
+}
+
+struct HashWriter<W: Write> {
+    inner: W,
+    sha256: Sha256,
+    md5: Md5,
+}
+
+impl<W: Write> HashWriter<W> {
+    fn new(inner: W) -> HashWriter<W> {
+        HashWriter {
+            inner,
+            sha256: Sha256::new(),
+            md5: Md5::new(),
+        }
+    }
+
+    fn finish(self) -> (W, Checksums) {
+        let checksums = Checksums {
+            sha256: hex::encode(self.sha256.finalize()),
+            md5: hex::encode(self.md5.finalize()),
+        };
+        (self.inner, checksums)
+    }
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.sha256.update(&buf[..written]);
+        self.md5.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Lists the files inside `folders`, relative to `base_dir`.
+fn list_files(base_dir: &Path, folders: &[String]) -> BTreeSet<PathBuf> {
+    folders
+        .iter()
+        .flat_map(|folder| WalkDir::new(base_dir.join(folder)).into_iter())
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            entry
+                .path()
+                .strip_prefix(base_dir)
+                .ok()
+                .map(|p| p.to_owned())
+        })
+        .collect()
+}
+
+fn is_temporary_dir(name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    name.starts_with(STAGING_PREFIX) || name.starts_with(BACKUP_PREFIX)
+}
+
+/// Moves the extracted `folders` from `staging_dir` into `addon_dir`. The
+/// previous folders are kept in a backup directory until all folders are in
+/// place and restored, if any of the moves fails.
+fn replace_folders(addon_dir: &Path, staging_dir: &Path, folders: &[String]) -> io::Result<()> {
+    let backup_dir = tempfile::Builder::new()
+        .prefix(BACKUP_PREFIX)
+        .tempdir_in(addon_dir)?;
+
+    let mut backed_up = vec![];
+    let mut moved = vec![];
+
+    let mut swap = || -> io::Result<()> {
+        for folder in folders.iter() {
+            let target = addon_dir.join(folder);
+
+            if target.exists() {
+                fs::rename(&target, backup_dir.path().join(folder))?;
+                backed_up.push(folder);
+            }
+
+            fs::rename(staging_dir.join(folder), &target)?;
+            moved.push(folder);
+        }
+
+        Ok(())
+    };
+    let result = swap();
+
+    if result.is_err() {
+        for folder in moved {
+            let _ = fs::rename(addon_dir.join(folder), staging_dir.join(folder));
+        }
+        for folder in backed_up {
+            let _ = fs::rename(backup_dir.path().join(folder), addon_dir.join(folder));
+        }
+    }
+
+    result
+}
+
 fn get_root_dir(path: &Path) -> PathBuf {
     match path.parent() {
         None => path.to_owned(),
@@ -250,6 +784,120 @@ fn get_root_dir(path: &Path) -> PathBuf {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_replace_folders_rolls_back() {
+        let addon_dir = tempfile::tempdir().unwrap();
+        let staging_dir = tempfile::tempdir().unwrap();
+
+        for name in ["CombatMetrics", "LibCombat"].iter() {
+            fs::create_dir(addon_dir.path().join(name)).unwrap();
+            fs::write(addon_dir.path().join(name).join("old.lua"), "").unwrap();
+        }
+        fs::create_dir(staging_dir.path().join("CombatMetrics")).unwrap();
+        fs::write(staging_dir.path().join("CombatMetrics").join("new.lua"), "").unwrap();
+
+        // LibCombat is missing in the staging directory, so the swap fails
+        let folders = vec!["CombatMetrics".to_owned(), "LibCombat".to_owned()];
+        assert!(replace_folders(addon_dir.path(), staging_dir.path(), &folders).is_err());
+
+        for name in ["CombatMetrics", "LibCombat"].iter() {
+            assert!(addon_dir.path().join(name).join("old.lua").exists());
+        }
+        assert!(!addon_dir
+            .path()
+            .join("CombatMetrics")
+            .join("new.lua")
+            .exists());
+
+        let folders = vec!["CombatMetrics".to_owned()];
+        assert!(replace_folders(addon_dir.path(), staging_dir.path(), &folders).is_ok());
+
+        assert!(addon_dir
+            .path()
+            .join("CombatMetrics")
+            .join("new.lua")
+            .exists());
+        assert!(!addon_dir
+            .path()
+            .join("CombatMetrics")
+            .join("old.lua")
+            .exists());
+        assert_eq!(fs::read_dir(addon_dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_list_files() {
+        let addon_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(addon_dir.path().join("CombatMetrics/lang")).unwrap();
+        fs::write(addon_dir.path().join("CombatMetrics/CombatMetrics.txt"), "").unwrap();
+        fs::write(addon_dir.path().join("CombatMetrics/lang/en.lua"), "").unwrap();
+        fs::create_dir_all(addon_dir.path().join("Other")).unwrap();
+        fs::write(addon_dir.path().join("Other/Other.txt"), "").unwrap();
+
+        let files = list_files(
+            addon_dir.path(),
+            &["CombatMetrics".to_owned(), "Missing".to_owned()],
+        );
+
+        assert_eq!(
+            files.into_iter().collect::<Vec<PathBuf>>(),
+            vec![
+                PathBuf::from("CombatMetrics/CombatMetrics.txt"),
+                PathBuf::from("CombatMetrics/lang/en.lua"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_folder_size() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("lang")).unwrap();
+        fs::write(dir.path().join("Addon.txt"), "## Title: Addon").unwrap();
+        fs::write(dir.path().join("lang").join("en.lua"), "-- en").unwrap();
+
+        assert_eq!(get_folder_size(dir.path()), (20, 2));
+        assert_eq!(get_folder_size(&dir.path().join("missing")), (0, 0));
+    }
+
+    #[test]
+    fn test_hash_writer() {
+        let mut writer = HashWriter::new(vec![]);
+        writer.write_all(b"abc").unwrap();
+        let (data, checksums) = writer.finish();
+
+        assert_eq!(data, b"abc");
+        assert_eq!(checksums.md5, "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            checksums.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_get_file_id() {
+        let tests: Vec<(&str, Option<u32>)> = vec![
+            (
+                "https://www.esoui.com/downloads/info1360-CombatMetrics.html",
+                Some(1360),
+            ),
+            ("https://www.esoui.com/downloads/download2275", Some(2275)),
+            ("https://www.esoui.com/downloads/fileinfo.php?id=7", Some(7)),
+            ("https://example.com/downloads/info7", None),
+        ];
+
+        for test in tests {
+            assert_eq!(get_file_id(ESOUI_URL, test.0), test.1, "{}", test.0);
+        }
+
+        assert_eq!(
+            get_file_id(
+                "http://127.0.0.1:8080/",
+                "http://127.0.0.1:8080/downloads/download7"
+            ),
+            Some(7)
+        );
+    }
+
     #[test]
     fn test_get_download_link() {
         let tests: Vec<(&str, Option<String>)> = vec![
@@ -268,8 +916,16 @@ mod tests {
         ];
 
         for test in tests {
-            let url = get_download_url(test.0);
+            let url = get_download_url(ESOUI_URL, test.0);
             assert!(url == test.1, "Got value: {:?}", url);
         }
+
+        assert_eq!(
+            get_download_url(
+                "http://127.0.0.1:8080/",
+                "http://127.0.0.1:8080/downloads/info7-LibAddonMenu.html"
+            ),
+            Some("http://127.0.0.1:8080/downloads/download7".to_string())
+        );
     }
 }
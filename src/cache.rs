@@ -0,0 +1,233 @@
+use super::errors::*;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Archive kept in the download cache. Archives are stored by their
+/// SHA-256, the esoui file ID and version are used to find them again.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CachedArchive {
+    pub name: String,
+    #[serde(rename = "fileId", default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    pub url: String,
+    pub sha256: String,
+    #[serde(default)]
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CacheIndex {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    archives: Vec<CachedArchive>,
+}
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+/// The default cache directory, e.g. `~/.cache/eso-addons` on Linux.
+pub fn get_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("eso-addons"))
+}
+
+impl Cache {
+    pub fn new(dir: &Path) -> Cache {
+        Cache {
+            dir: dir.to_owned(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cached archives, the most recently cached last.
+    pub fn list(&self) -> Result<Vec<CachedArchive>> {
+        Ok(self.read_index()?.archives)
+    }
+
+    /// The most recently cached archive of a file ID. If `version` is set,
+    /// only archives of that version are considered.
+    pub fn find(&self, file_id: u32, version: Option<&str>) -> Result<Option<CachedArchive>> {
+        let found = self
+            .list()?
+            .into_iter()
+            .rev()
+            .filter(|archive| archive.file_id == Some(file_id))
+            .find(|archive| version.is_none() || archive.version.as_deref() == version);
+        Ok(found)
+    }
+
+    /// Opens the archive with the given hash. Archives, which don't match
+    /// their hash anymore, are removed and treated as missing.
+    pub fn get(&self, sha256: &str) -> Result<Option<File>> {
+        let path = self.archive_path(sha256);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut file = File::open(&path).map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher).map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+
+        if hex::encode(hasher.finalize()) != sha256 {
+            fs::remove_file(&path).map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+            return Ok(None);
+        }
+
+        file.seek(SeekFrom::Start(0))
+            .map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+        Ok(Some(file))
+    }
+
+    /// Copies the archive into the cache and records it in the index.
+    pub fn insert(&self, archive: &mut File, mut record: CachedArchive) -> Result<()> {
+        let path = self.archive_path(&record.sha256);
+
+        if !path.exists() {
+            fs::create_dir_all(self.archives_dir())
+                .map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+
+            // write to a temporary file first, so that an interrupted copy
+            // never leaves a truncated archive under the final name
+            let mut tmp = tempfile::NamedTempFile::new_in(self.archives_dir())
+                .map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+            archive
+                .seek(SeekFrom::Start(0))
+                .map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+            io::copy(archive, &mut tmp).map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+            tmp.persist(&path)
+                .map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+        }
+
+        record.size = fs::metadata(&path)
+            .map_err(|err| Error::CannotUseCache(Box::new(err)))?
+            .len();
+
+        let mut index = self.read_index()?;
+        index
+            .archives
+            .retain(|archive| archive.sha256 != record.sha256);
+        index.archives.push(record);
+        self.write_index(&index)
+    }
+
+    /// Removes all archives except the ones with a hash from `keep`.
+    pub fn prune(&self, keep: &[&str]) -> Result<Vec<CachedArchive>> {
+        let mut index = self.read_index()?;
+        let (kept, removed): (Vec<CachedArchive>, Vec<CachedArchive>) = index
+            .archives
+            .into_iter()
+            .partition(|archive| keep.contains(&archive.sha256.as_str()));
+
+        for archive in removed.iter() {
+            let path = self.archive_path(&archive.sha256);
+            if path.exists() {
+                fs::remove_file(path).map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+            }
+        }
+
+        index.archives = kept;
+        self.write_index(&index)?;
+        Ok(removed)
+    }
+
+    fn archives_dir(&self) -> PathBuf {
+        self.dir.join("archives")
+    }
+
+    fn archive_path(&self, sha256: &str) -> PathBuf {
+        self.archives_dir().join(format!("{}.zip", sha256))
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("index.toml")
+    }
+
+    fn read_index(&self) -> Result<CacheIndex> {
+        let path = self.index_path();
+        if !path.exists() {
+            return Ok(CacheIndex::default());
+        }
+
+        let data = fs::read_to_string(path).map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+        toml::from_str(&data).map_err(|err| Error::CannotUseCache(Box::new(err)))
+    }
+
+    fn write_index(&self, index: &CacheIndex) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+        let data = toml::to_string(index).map_err(|err| Error::CannotUseCache(Box::new(err)))?;
+        fs::write(self.index_path(), data).map_err(|err| Error::CannotUseCache(Box::new(err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn archive(content: &[u8]) -> (File, String) {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(content).unwrap();
+        (file, hex::encode(Sha256::digest(content)))
+    }
+
+    fn record(sha256: &str, version: &str) -> CachedArchive {
+        CachedArchive {
+            name: "LibAddonMenu-2.0".to_owned(),
+            file_id: Some(7),
+            version: Some(version.to_owned()),
+            updated: None,
+            url: "https://cdn.esoui.com/downloads/file7/LibAddonMenu.zip".to_owned(),
+            sha256: sha256.to_owned(),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path());
+
+        let (mut old, old_sha256) = archive(b"r31");
+        let (mut new, new_sha256) = archive(b"r32");
+        cache
+            .insert(&mut old, record(&old_sha256, "2.0 r31"))
+            .unwrap();
+        cache
+            .insert(&mut new, record(&new_sha256, "2.0 r32"))
+            .unwrap();
+
+        assert_eq!(cache.list().unwrap().len(), 2);
+        assert_eq!(cache.list().unwrap()[0].size, 3);
+        assert_eq!(cache.find(7, None).unwrap().unwrap().sha256, new_sha256);
+        assert_eq!(
+            cache.find(7, Some("2.0 r31")).unwrap().unwrap().sha256,
+            old_sha256
+        );
+        assert!(cache.find(8, None).unwrap().is_none());
+        assert!(cache.get(&old_sha256).unwrap().is_some());
+
+        // corrupted archives are dropped
+        fs::write(cache.archive_path(&new_sha256), b"broken").unwrap();
+        assert!(cache.get(&new_sha256).unwrap().is_none());
+
+        let removed = cache.prune(&[&old_sha256]).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(
+            cache.list().unwrap(),
+            vec![record(&old_sha256, "2.0 r31")]
+                .into_iter()
+                .map(|r| CachedArchive { size: 3, ..r })
+                .collect::<Vec<_>>()
+        );
+        assert!(cache.get(&old_sha256).unwrap().is_some());
+    }
+}
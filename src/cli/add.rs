@@ -3,54 +3,111 @@ use eso_addons::{
     addons,
     addons::Manager,
     config::{self, AddonEntry, Config},
+    errors::Error as AppError,
     htmlparser,
+    lock::{self, LockedAddon},
 };
 use std::path::Path;
 
-use super::{Error, Result};
+use super::progress::DownloadProgress;
+use super::{dependencies, Error, Result};
 
 #[derive(Parser)]
 pub struct AddCommand {
+    #[clap(value_parser)]
     addon_url: Option<String>,
     #[clap(
         short,
         long,
+        action,
         help = "Indicate, if the addon is only a dependency for another addon"
     )]
-    #[clap(short)]
     dependency: bool,
+    #[clap(
+        long,
+        value_parser,
+        help = "Pin the addon to a version from its file history"
+    )]
+    pin: Option<String>,
+    #[clap(long, action, help = "Don't install missing dependencies")]
+    no_deps: bool,
+    #[clap(
+        short,
+        long,
+        action,
+        help = "Install missing dependencies without asking"
+    )]
+    yes: bool,
 }
 
 impl AddCommand {
+    /// Adds the addon at `addon_url`, e.g. one picked from search results.
+    pub fn with_url(addon_url: &str, dependency: bool, no_deps: bool, yes: bool) -> AddCommand {
+        AddCommand {
+            addon_url: Some(addon_url.to_owned()),
+            dependency,
+            pin: None,
+            no_deps,
+            yes,
+        }
+    }
+
     pub fn run(
         &mut self,
         cfg: &mut Config,
         config_filepath: &Path,
         addon_manager: &Manager,
     ) -> Result<()> {
-        let mut entry = self.get_entry()?;
+        let mut entry = self.get_entry(addon_manager)?;
 
         if cfg.addons.iter().find(|el| el.url == entry.url).is_some() {
             println!("Addon {} is already installed", &entry.name);
             return Ok(());
         }
 
-        let installed = addon_manager.download_addon(&entry.url.clone().unwrap())?;
-
-        if entry.name != installed.name {
-            entry.name = installed.name;
+        let remote = addon_manager.get_remote_addon(&entry.url.clone().unwrap())?;
+        let progress = DownloadProgress::new(1);
+        let archive = addon_manager.fetch_remote_addon(
+            &remote,
+            entry.version.as_deref(),
+            &progress.transfer(&entry.name),
+        );
+        progress.finish();
+        let download = addon_manager.install_archive(archive?)?;
+
+        if entry.name != download.addon.name {
+            entry.name = download.addon.name.clone();
         }
+        entry.folders = download.folders.clone();
 
         cfg.addons.push(entry.clone());
 
-        config::save_config(config_filepath, &cfg)?;
+        config::save_config(config_filepath, cfg)?;
+
+        let lock_filepath = lock::get_lock_file_path(config_filepath);
+        let mut lock_file = lock::parse_lock_file(&lock_filepath)?;
+        lock_file.insert(LockedAddon::new(&entry.name, &download));
+        lock::save_lock_file(&lock_filepath, &lock_file)?;
 
         println!("🎊 Installed {}!", &entry.name);
+        if !download.bundled.is_empty() {
+            let bundled: Vec<&str> = download.bundled.iter().map(|a| a.name.as_str()).collect();
+            println!("   also installed {}", bundled.join(", "));
+        }
+
+        if !self.no_deps {
+            dependencies::install_missing_dependencies(
+                cfg,
+                config_filepath,
+                addon_manager,
+                self.yes,
+            )?;
+        }
 
         Ok(())
     }
 
-    pub fn get_entry(&mut self) -> Result<AddonEntry> {
+    pub fn get_entry(&mut self, addon_manager: &Manager) -> Result<AddonEntry> {
         if self.addon_url.is_none() {
             self.ask_for_fields()?;
         }
@@ -61,16 +118,22 @@ impl AddCommand {
             .ok_or(Error::Other("missing addon URL".into()))?;
         let dependency = self.dependency;
 
-        let addon_name = htmlparser::get_document(&addon_url)
-            .map(htmlparser::get_addon_name)?
+        if addon_manager.is_offline() {
+            return Err(AppError::Offline(addon_url).into());
+        }
+
+        let addon_name = htmlparser::get_document(addon_manager.client(), &addon_url)
+            .map(|doc| htmlparser::get_addon_name(&doc))?
             .ok_or(Error::Other("failed to get addon name".into()))?;
 
-        let download_url = addons::get_download_url(&addon_url);
+        let download_url = addons::get_download_url(addon_manager.client().base_url(), &addon_url);
 
         Ok(AddonEntry {
             name: addon_name,
             url: download_url,
-            dependency: dependency,
+            dependency,
+            version: self.pin.clone(),
+            folders: vec![],
         })
     }
 
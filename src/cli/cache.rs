@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use colored::*;
+use eso_addons::{addons::Manager, cache::Cache, lock};
+
+use super::{format_size, new_table, Error, Result};
+
+#[derive(Parser)]
+pub struct CacheCommand {
+    #[clap(subcommand)]
+    subcmd: CacheSubCommand,
+}
+
+#[derive(Parser)]
+enum CacheSubCommand {
+    #[clap(about = "List cached archives")]
+    List,
+    #[clap(about = "Remove cached archives, which are not in the lock file")]
+    Prune(PruneCommand),
+}
+
+#[derive(Parser)]
+struct PruneCommand {
+    #[clap(long, action, help = "Remove all cached archives")]
+    all: bool,
+}
+
+impl CacheCommand {
+    pub fn run(&self, config_filepath: &Path, addon_manager: &Manager) -> Result<()> {
+        let cache = addon_manager
+            .cache()
+            .ok_or_else(|| Error::Other("cannot find the cache directory".into()))?;
+
+        match &self.subcmd {
+            CacheSubCommand::List => list(cache),
+            CacheSubCommand::Prune(prune) => prune.run(cache, config_filepath),
+        }
+    }
+}
+
+fn list(cache: &Cache) -> Result<()> {
+    let archives = cache.list()?;
+
+    if archives.is_empty() {
+        println!("The download cache is empty");
+    } else {
+        let mut table = new_table();
+        table.set_titles(row![
+            "Name".bold(),
+            "Version".bold(),
+            "Released".bold(),
+            "Size".bold(),
+            "SHA-256".bold()
+        ]);
+
+        for archive in archives.iter() {
+            table.add_row(row![
+                archive.name,
+                archive.version.clone().unwrap_or_default(),
+                archive.updated.clone().unwrap_or_default(),
+                format_size(archive.size),
+                &archive.sha256[..12.min(archive.sha256.len())]
+            ]);
+        }
+
+        table.printstd();
+    }
+
+    let total: u64 = archives.iter().map(|archive| archive.size).sum();
+    let msg = format!("{} ({})", cache.dir().display(), format_size(total));
+    println!("{}", msg.truecolor(130, 130, 130));
+
+    Ok(())
+}
+
+impl PruneCommand {
+    fn run(&self, cache: &Cache, config_filepath: &Path) -> Result<()> {
+        let lock_file = lock::parse_lock_file(&lock::get_lock_file_path(config_filepath))?;
+        let keep: Vec<&str> = if self.all {
+            vec![]
+        } else {
+            lock_file
+                .addons
+                .iter()
+                .map(|locked| locked.sha256.as_str())
+                .collect()
+        };
+
+        let removed = cache.prune(&keep)?;
+        for archive in removed.iter() {
+            let msg = format!(
+                "  - removed {} {}",
+                archive.name,
+                archive.version.clone().unwrap_or_default()
+            );
+            println!("{}", msg.truecolor(130, 130, 130));
+        }
+
+        let freed: u64 = removed.iter().map(|archive| archive.size).sum();
+        println!(
+            "{} Removed {} archive(s), {} freed",
+            "✔".green(),
+            removed.len(),
+            format_size(freed)
+        );
+
+        Ok(())
+    }
+}
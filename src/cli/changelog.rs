@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use colored::*;
+use eso_addons::{
+    addons::Manager, config::Config, errors::Error as AppError, htmlparser,
+    htmlparser::ChangelogEntry, lock,
+};
+
+use super::{Error, Result};
+
+#[derive(Parser)]
+pub struct ChangelogCommand {
+    #[clap(value_parser)]
+    name: String,
+    #[clap(
+        short,
+        long,
+        action,
+        help = "Show the whole changelog, not only the changes since the installed version"
+    )]
+    all: bool,
+}
+
+impl ChangelogCommand {
+    pub fn run(
+        &self,
+        config: &Config,
+        config_filepath: &Path,
+        addon_manager: &Manager,
+    ) -> Result<()> {
+        let entry = config.addons.iter().find(|entry| entry.name == self.name);
+        let lock_file = lock::parse_lock_file(&lock::get_lock_file_path(config_filepath))?;
+        let locked = lock_file.get(&self.name);
+
+        let base_url = addon_manager.client().base_url();
+        let url = entry
+            .and_then(|entry| entry.url.clone())
+            .or_else(|| {
+                locked
+                    .and_then(|locked| locked.file_id)
+                    .map(|file_id| htmlparser::get_info_url(base_url, file_id))
+            })
+            .ok_or_else(|| Error::AddonNotFound(self.name.clone()))?;
+
+        if addon_manager.is_offline() {
+            return Err(AppError::Offline(url).into());
+        }
+
+        let remote = addon_manager.get_remote_addon(&url)?;
+        if remote.changelog.is_empty() {
+            println!("{} has no changelog on esoui.com", self.name);
+            return Ok(());
+        }
+
+        let installed = addon_manager.get_addon(&self.name)?;
+        let installed_version = eso_addons::get_installed_version(locked, installed.as_ref());
+
+        let changes = match (entry, installed_version) {
+            (Some(entry), Some(_)) if !self.all => {
+                eso_addons::get_pending_changes(entry, &remote, installed_version)
+            }
+            _ => &remote.changelog[..],
+        };
+
+        if changes.is_empty() {
+            println!(
+                "{} {}",
+                "✔".green(),
+                format!(
+                    "No changes since {} {}",
+                    self.name,
+                    installed_version.unwrap_or_default()
+                )
+                .truecolor(130, 130, 130)
+            );
+            return Ok(());
+        }
+
+        println!("{}", self.name.bold());
+        print_changes(changes);
+
+        Ok(())
+    }
+}
+
+/// Prints the changelog entries indented below the addon.
+pub fn print_changes(changes: &[ChangelogEntry]) {
+    for change in changes {
+        println!("  {}", change.version.bold());
+        for line in change.changes.iter() {
+            println!("    {}", line);
+        }
+    }
+}
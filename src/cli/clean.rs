@@ -3,10 +3,11 @@ use colored::*;
 use eso_addons::{
     addons::{Addon, Manager},
     config::Config,
+    graph::DependencyGraph,
 };
 #[derive(Parser)]
 pub struct CleanCommand {
-    #[clap(long)]
+    #[clap(long, value_parser)]
     remove: Option<bool>,
 }
 
@@ -20,16 +21,17 @@ impl CleanCommand {
         let installed_addons_list = addon_manager.get_addons()?;
 
         let unmanaged =
-            eso_addons::get_unmanaged_addons(&desired_addons, installed_addons_list.addons.iter());
+            eso_addons::get_unmanaged_addons(desired_addons, installed_addons_list.addons.iter());
+        let graph = DependencyGraph::new(&installed_addons_list.addons);
 
-        if unmanaged.len() > 0 {
+        if !unmanaged.is_empty() {
             match self.remove {
                 Some(true) => self.remove_addons(addon_manager, unmanaged.iter())?,
-                Some(false) => self.show_addons_to_remove(unmanaged.iter()),
+                Some(false) => self.show_addons_to_remove(&graph, &unmanaged),
                 None => {
-                    self.show_addons_to_remove(unmanaged.iter());
+                    self.show_addons_to_remove(&graph, &unmanaged);
                     if self.ask_for_remove_confirmation()? {
-                        println!("");
+                        println!();
                         self.remove_addons(addon_manager, unmanaged.iter())?;
                     }
                 }
@@ -49,17 +51,32 @@ impl CleanCommand {
         Ok(answer.as_bool().unwrap_or(false))
     }
 
-    fn show_addons_to_remove<'a, I>(&self, addons: I)
-    where
-        I: Iterator<Item = &'a &'a Addon>,
-    {
+    fn show_addons_to_remove(&self, graph: &DependencyGraph, addons: &[&Addon]) {
         println!("{} Addons to remove:", "🗑".red());
 
         for addon in addons {
-            println!("- {}", addon.name)
+            let mut line = match addon.manifest.title {
+                Some(ref title) if title != &addon.name => format!("- {} ({})", addon.name, title),
+                _ => format!("- {}", addon.name),
+            };
+
+            let needed_by: Vec<&str> = graph
+                .dependents(&addon.name)
+                .iter()
+                .map(|edge| edge.from.as_str())
+                .filter(|name| !addons.iter().any(|a| &a.name == name))
+                .collect();
+            if !needed_by.is_empty() {
+                line.push_str(&format!(
+                    " {}",
+                    format!("needed by {}", needed_by.join(", ")).yellow()
+                ));
+            }
+
+            println!("{}", line);
         }
 
-        println!("")
+        println!()
     }
 
     fn remove_addons<'a, I>(
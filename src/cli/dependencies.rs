@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use colored::*;
+use eso_addons::{
+    addons::{self, Manager},
+    config::{self, AddonEntry, Config},
+    htmlparser::{self, SearchResult},
+    http::EsouiClient,
+    lock::{self, LockedAddon},
+};
+
+use super::{Error, Result};
+
+/// Looks up missing dependencies on esoui.com and installs them, until
+/// nothing is missing or every remaining dependency was skipped.
+pub fn install_missing_dependencies(
+    config: &mut Config,
+    config_filepath: &Path,
+    addon_manager: &Manager,
+    assume_yes: bool,
+) -> Result<()> {
+    let mut attempted: HashSet<String> = HashSet::new();
+
+    loop {
+        let installed_addons_list = addon_manager.get_addons()?;
+        let mut missing: Vec<String> =
+            eso_addons::get_missing_dependencies(&installed_addons_list.addons)
+                .filter(|name| !attempted.contains(name))
+                .collect();
+        missing.sort();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        for name in missing {
+            attempted.insert(name.clone());
+
+            let candidate = match find_dependency(addon_manager.client(), &name, assume_yes) {
+                Ok(Some(candidate)) => candidate,
+                Ok(None) => continue,
+                Err(err) => {
+                    println!("{} Failed {}!", "☒".red(), name);
+                    println!("{}", err);
+                    continue;
+                }
+            };
+
+            let download_url =
+                match addons::get_download_url(addon_manager.client().base_url(), &candidate.url) {
+                    Some(url) => url,
+                    None => continue,
+                };
+
+            let download = match addon_manager.download_addon(&download_url, None) {
+                Ok(download) => download,
+                Err(err) => {
+                    println!("{} Failed {}!", "☒".red(), name);
+                    println!("{}", err);
+                    continue;
+                }
+            };
+
+            let installed = &download.addon;
+
+            if !config
+                .addons
+                .iter()
+                .any(|entry| entry.url.as_ref() == Some(&download_url))
+            {
+                config.addons.push(AddonEntry {
+                    name: installed.name.clone(),
+                    url: Some(download_url),
+                    dependency: true,
+                    version: None,
+                    folders: download.folders.clone(),
+                });
+                config::save_config(config_filepath, config)?;
+            }
+
+            let lock_filepath = lock::get_lock_file_path(config_filepath);
+            let mut lock_file = lock::parse_lock_file(&lock_filepath)?;
+            lock_file.insert(LockedAddon::new(&installed.name, &download));
+            lock::save_lock_file(&lock_filepath, &lock_file)?;
+
+            if installed.name == name {
+                println!("{} Installed dependency {}!", "✔".green(), name);
+            } else {
+                println!(
+                    "⚠ Installed {} for dependency {}, but the names differ.",
+                    installed.name, name
+                );
+            }
+        }
+    }
+}
+
+fn find_dependency(
+    client: &dyn EsouiClient,
+    name: &str,
+    assume_yes: bool,
+) -> Result<Option<SearchResult>> {
+    let query = eso_addons::normalize_addon_name(name);
+    let results = htmlparser::search_addons(client, &query)?;
+    let candidates = eso_addons::get_dependency_candidates(name, results);
+
+    if candidates.is_empty() {
+        println!("⚠ Cannot find dependency {} on esoui.com", name);
+        return Ok(None);
+    }
+
+    if assume_yes {
+        let best = candidates.into_iter().next().unwrap();
+        if eso_addons::normalize_addon_name(&best.name) == query {
+            return Ok(Some(best));
+        }
+
+        println!("⚠ No exact match for dependency {} on esoui.com", name);
+        return Ok(None);
+    }
+
+    let mut choices: Vec<String> = candidates
+        .iter()
+        .map(|candidate| format!("{} ({})", candidate.name, candidate.url))
+        .collect();
+    choices.push("Skip".to_owned());
+
+    let question = requestty::Question::select("dependency")
+        .message(format!("Install missing dependency {}?", name))
+        .choices(choices)
+        .build();
+
+    let answer = requestty::prompt_one(question).map_err(|err| Error::Other(Box::new(err)))?;
+    let index = answer.as_list_item().map(|item| item.index);
+
+    Ok(index.and_then(|index| candidates.into_iter().nth(index)))
+}
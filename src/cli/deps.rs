@@ -0,0 +1,61 @@
+use colored::*;
+use eso_addons::addons::Manager;
+use eso_addons::graph::{DependencyGraph, DependencyKind, DependencyTree};
+
+use super::{Error, Result};
+
+#[derive(Parser)]
+pub struct DepsCommand {
+    #[clap(value_parser)]
+    name: String,
+}
+
+impl DepsCommand {
+    pub fn run(&self, addon_manager: &Manager) -> Result<()> {
+        let installed_addons_list = addon_manager.get_addons()?;
+        let graph = DependencyGraph::new(&installed_addons_list.addons);
+
+        if !graph.is_installed(&self.name) {
+            return Err(Error::AddonNotFound(self.name.clone()));
+        }
+
+        let tree = graph.tree(&self.name).unwrap();
+
+        println!("{}", tree.name.bold());
+        print_dependencies(&tree.dependencies, "");
+
+        Ok(())
+    }
+}
+
+fn print_dependencies(dependencies: &[DependencyTree], prefix: &str) {
+    for (i, dependency) in dependencies.iter().enumerate() {
+        let (branch, indent) = if i == dependencies.len() - 1 {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        println!("{}{}{}", prefix, branch, describe(dependency));
+        print_dependencies(&dependency.dependencies, &format!("{}{}", prefix, indent));
+    }
+}
+
+fn describe(dependency: &DependencyTree) -> String {
+    let mut line = match dependency.constraint {
+        Some(constraint) => format!("{} ({})", dependency.name, constraint),
+        None => dependency.name.clone(),
+    };
+
+    if dependency.kind == DependencyKind::Optional {
+        line.push_str(&format!(" {}", "optional".truecolor(130, 130, 130)));
+    }
+    if !dependency.installed {
+        line.push_str(&format!(" {}", "MISSING".red()));
+    }
+    if dependency.cycle {
+        line.push_str(&format!(" {}", "CYCLE".yellow()));
+    }
+
+    line
+}
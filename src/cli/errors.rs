@@ -1,7 +1,11 @@
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     AddonNotFound(String),
     NoAddonsInstalled,
+    NotLocked(String),
+    UpdatesAvailable(usize),
+    UpdateCheckFailed(usize),
     AppError(eso_addons::errors::Error),
     Other(Box<dyn std::error::Error>),
 }
@@ -11,12 +15,32 @@ impl std::fmt::Display for Error {
         match &self {
             Self::AddonNotFound(name) => f.write_str(&format!("addon {} not found", &name)),
             Self::NoAddonsInstalled => f.write_str("no addons installed"),
+            Self::UpdatesAvailable(count) => {
+                f.write_str(&format!("{} addon update(s) available", count))
+            }
+            Self::UpdateCheckFailed(count) => {
+                f.write_str(&format!("cannot check {} addon(s) for updates", count))
+            }
+            Self::NotLocked(name) => {
+                f.write_str(&format!("addon {} is not in the lock file", &name))
+            }
             Self::AppError(err) => f.write_str(&format!("app error: {}", err)),
             Self::Other(err) => f.write_str(&format!("other error: {}", err)),
         }
     }
 }
 
+impl Error {
+    /// Available updates have their own exit code, so that scripts can tell
+    /// them apart from failures.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::UpdatesAvailable(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
 impl std::error::Error for Error {}
 
 impl From<eso_addons::errors::Error> for Error {
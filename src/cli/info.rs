@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use colored::*;
+use eso_addons::{
+    addons::{self, Addon, Manager, RemoteAddon},
+    config::{AddonEntry, Config},
+    htmlparser,
+    lock::{self, LockedAddon},
+};
+
+use super::{format_size, Error, Result};
+
+#[derive(Parser)]
+pub struct InfoCommand {
+    #[clap(value_parser)]
+    name: String,
+}
+
+impl InfoCommand {
+    pub fn run(
+        &self,
+        config: &Config,
+        config_filepath: &Path,
+        addon_manager: &Manager,
+    ) -> Result<()> {
+        let installed = addon_manager.get_addon(&self.name)?;
+        let entry = config.addons.iter().find(|entry| entry.name == self.name);
+        let lock_file = lock::parse_lock_file(&lock::get_lock_file_path(config_filepath))?;
+        let locked = lock_file.get(&self.name);
+
+        if installed.is_none() && entry.is_none() && locked.is_none() {
+            return Err(Error::AddonNotFound(self.name.clone()));
+        }
+
+        println!("{}", self.name.bold());
+
+        match installed {
+            Some(ref addon) => print_installed(addon),
+            None => println!("{}", "NOT INSTALLED".truecolor(200, 200, 0)),
+        }
+
+        if let Some(remote) = self.get_remote_addon(addon_manager, entry, locked) {
+            print_remote(&remote);
+        }
+        if let Some(entry) = entry {
+            print_entry(entry);
+        }
+        if let Some(locked) = locked {
+            print_locked(locked);
+        }
+
+        Ok(())
+    }
+
+    // esoui.com is optional here, the local details are shown anyway.
+    fn get_remote_addon(
+        &self,
+        addon_manager: &Manager,
+        entry: Option<&AddonEntry>,
+        locked: Option<&LockedAddon>,
+    ) -> Option<RemoteAddon> {
+        if addon_manager.is_offline() {
+            return None;
+        }
+
+        let base_url = addon_manager.client().base_url();
+        let url = entry.and_then(|entry| entry.url.clone()).or_else(|| {
+            locked
+                .and_then(|locked| locked.file_id)
+                .map(|file_id| htmlparser::get_info_url(base_url, file_id))
+        })?;
+
+        match addon_manager.get_remote_addon(&url) {
+            Ok(remote) => Some(remote),
+            Err(err) => {
+                println!();
+                println!("⚠ Cannot get {} from esoui.com: {}", self.name, err);
+                None
+            }
+        }
+    }
+}
+
+fn print_installed(addon: &Addon) {
+    let manifest = &addon.manifest;
+    let (size, files) = addons::get_folder_size(&addon.path);
+
+    let version = match (&manifest.version, manifest.addon_version) {
+        (Some(version), Some(addon_version)) => format!("{} ({})", version, addon_version),
+        (Some(version), None) => version.clone(),
+        (None, Some(addon_version)) => addon_version.to_string(),
+        (None, None) => String::new(),
+    };
+    let mut saved_variables = manifest.saved_variables.clone();
+    saved_variables.extend(
+        manifest
+            .saved_variables_per_character
+            .iter()
+            .map(|name| format!("{} (per character)", name)),
+    );
+
+    print_section(
+        "Installed",
+        &[
+            ("Title", manifest.title.clone().unwrap_or_default()),
+            ("Author", manifest.author.clone().unwrap_or_default()),
+            ("Version", version),
+            ("API version", join(manifest.api_version.iter())),
+            ("Depends on", join(manifest.depends_on.iter())),
+            ("Optional", join(manifest.optional_depends_on.iter())),
+            ("SavedVariables", saved_variables.join(", ")),
+            ("Library", yes_no(manifest.is_library)),
+            ("Size", format!("{} in {} files", format_size(size), files)),
+            ("Path", addon.path.display().to_string()),
+        ],
+    );
+}
+
+fn print_remote(remote: &RemoteAddon) {
+    print_section(
+        "esoui.com",
+        &[
+            ("Latest version", remote.version.clone().unwrap_or_default()),
+            ("Updated", remote.updated.clone().unwrap_or_default()),
+            (
+                "Compatibility",
+                remote.compatibility.clone().unwrap_or_default(),
+            ),
+            ("URL", remote.url.clone()),
+            (
+                "Description",
+                remote.description.clone().unwrap_or_default(),
+            ),
+        ],
+    );
+}
+
+fn print_entry(entry: &AddonEntry) {
+    print_section(
+        "Config",
+        &[
+            ("URL", entry.url.clone().unwrap_or_default()),
+            ("Dependency", yes_no(entry.dependency)),
+            ("Pinned", entry.version.clone().unwrap_or_default()),
+            ("Folders", entry.folders.join(", ")),
+        ],
+    );
+}
+
+fn print_locked(locked: &LockedAddon) {
+    print_section(
+        "Lock file",
+        &[
+            ("Version", locked.version.clone().unwrap_or_default()),
+            ("Updated", locked.updated.clone().unwrap_or_default()),
+            (
+                "File ID",
+                locked.file_id.map(|id| id.to_string()).unwrap_or_default(),
+            ),
+            ("Archive", locked.url.clone()),
+            ("SHA-256", locked.sha256.clone()),
+            ("Folders", locked.folders.join(", ")),
+        ],
+    );
+}
+
+/// Prints the non-empty fields below a title, continuation lines of
+/// multi-line values are indented.
+fn print_section(title: &str, fields: &[(&str, String)]) {
+    println!();
+    println!("{}", title.bold());
+
+    for (label, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+        let mut lines = value.lines();
+        let label = format!("{:<16}", label);
+        println!(
+            "  {}{}",
+            label.truecolor(130, 130, 130),
+            lines.next().unwrap()
+        );
+        for line in lines {
+            println!("  {:<16}{}", "", line);
+        }
+    }
+}
+
+fn join<T: ToString>(values: impl Iterator<Item = T>) -> String {
+    values
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_owned()
+}
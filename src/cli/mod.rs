@@ -1,15 +1,27 @@
 use clap::Parser;
-use dirs;
 use eso_addons::addons;
+use eso_addons::cache::Cache;
 use eso_addons::config;
+use eso_addons::http::HttpClient;
+use prettytable::{format, Table};
+use std::env;
 use std::path::PathBuf;
 
 mod add;
+mod cache;
+mod changelog;
 mod clean;
+mod dependencies;
+mod deps;
 mod errors;
+mod info;
 mod list;
+mod outdated;
+mod progress;
 mod remove;
+mod search;
 mod update;
+mod why;
 
 use errors::{Error, Result};
 
@@ -20,8 +32,15 @@ use errors::{Error, Result};
     about = "CLI tool for managing addons for The Elder Scrolls Online"
 )]
 struct Opts {
-    #[clap(short, long, help = "Path to TOML config file")]
+    #[clap(short, long, value_parser, help = "Path to TOML config file")]
     config: Option<String>,
+    #[clap(
+        long,
+        global = true,
+        action,
+        help = "Install addons from the download cache only"
+    )]
+    offline: bool,
     #[clap(subcommand)]
     subcmd: SubCommand,
 }
@@ -32,12 +51,26 @@ enum SubCommand {
     List(list::ListCommand),
     #[clap(about = "Update addons")]
     Update(update::UpdateCommand),
+    #[clap(about = "List addons with available updates")]
+    Outdated(outdated::OutdatedCommand),
     #[clap(about = "Uninstall not managed addons")]
     Clean(clean::CleanCommand),
     #[clap(about = "Add a new addon")]
     Add(add::AddCommand),
+    #[clap(about = "Search addons on esoui.com and install one")]
+    Search(search::SearchCommand),
     #[clap(about = "Uninstall addon")]
     Remove(remove::RemoveCommand),
+    #[clap(about = "Show details of an addon")]
+    Info(info::InfoCommand),
+    #[clap(about = "Show the changes of an addon since the installed version")]
+    Changelog(changelog::ChangelogCommand),
+    #[clap(about = "Show the dependency tree of an addon")]
+    Deps(deps::DepsCommand),
+    #[clap(about = "Show which addons depend on an addon")]
+    Why(why::WhyCommand),
+    #[clap(about = "Manage the download cache")]
+    Cache(cache::CacheCommand),
 }
 
 pub fn run() -> Result<()> {
@@ -53,15 +86,59 @@ pub fn run() -> Result<()> {
 
     let mut config = config::parse_config(&config_filepath)?;
 
-    let addon_manager = addons::Manager::new(&config.addon_dir);
+    let http_settings = config.http.clone().with_env(|name| env::var_os(name));
+    let http_client = HttpClient::new(&http_settings).map_err(|err| Error::Other(Box::new(err)))?;
+    let mut addon_manager = addons::Manager::new(&config.addon_dir)
+        .with_client(http_client)
+        .with_extract_limits(config.extract.clone())
+        .with_offline(opts.offline);
+    if let Some(cache_dir) = eso_addons::cache::get_cache_dir() {
+        addon_manager = addon_manager.with_cache(Cache::new(&cache_dir));
+    }
 
     match opts.subcmd {
         SubCommand::List(list) => list.run(&addon_manager, &config),
-        SubCommand::Update(update) => update.run(&config, &addon_manager),
-        SubCommand::Clean(mut clean) => clean
-            .run(&config, &addon_manager)
-            .map_err(|err| Error::Other(err)),
+        SubCommand::Outdated(outdated) => outdated.run(&config, &config_filepath, &addon_manager),
+        SubCommand::Update(update) => update.run(&mut config, &config_filepath, &addon_manager),
+        SubCommand::Clean(mut clean) => clean.run(&config, &addon_manager).map_err(Error::Other),
         SubCommand::Add(mut add) => add.run(&mut config, &config_filepath, &addon_manager),
+        SubCommand::Search(search) => search.run(&mut config, &config_filepath, &addon_manager),
         SubCommand::Remove(remove) => remove.run(&mut config, &config_filepath, &addon_manager),
+        SubCommand::Info(info) => info.run(&config, &config_filepath, &addon_manager),
+        SubCommand::Changelog(changelog) => {
+            changelog.run(&config, &config_filepath, &addon_manager)
+        }
+        SubCommand::Deps(deps) => deps.run(&addon_manager),
+        SubCommand::Why(why) => why.run(&addon_manager, &config),
+        SubCommand::Cache(cache) => cache.run(&config_filepath, &addon_manager),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1048576.0),
     }
 }
+
+fn new_table() -> Table {
+    let mut table = Table::new();
+
+    let format = format::FormatBuilder::new()
+        .column_separator('|')
+        .borders('|')
+        .padding(1, 1)
+        .separators(
+            &[
+                format::LinePosition::Top,
+                format::LinePosition::Bottom,
+                format::LinePosition::Title,
+            ],
+            format::LineSeparator::new('-', '+', '+', '+'),
+        )
+        .build();
+    table.set_format(format);
+
+    table
+}
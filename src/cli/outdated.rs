@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use colored::*;
+use eso_addons::{addons::Manager, config::Config, htmlparser, lock};
+
+use super::changelog;
+use super::{new_table, Error, Result};
+
+#[derive(Parser)]
+pub struct OutdatedCommand {
+    #[clap(long, action, help = "Show the changes since the installed versions")]
+    show_changelog: bool,
+}
+
+impl OutdatedCommand {
+    pub fn run(
+        &self,
+        config: &Config,
+        config_filepath: &Path,
+        addon_manager: &Manager,
+    ) -> Result<()> {
+        let lock_file = lock::parse_lock_file(&lock::get_lock_file_path(config_filepath))?;
+        let installed_addons_list = addon_manager.get_addons()?;
+
+        let mut table = new_table();
+        table.set_titles(row![
+            "Name".bold(),
+            "Installed".bold(),
+            "Latest".bold(),
+            "Released".bold(),
+            "URL".bold()
+        ]);
+
+        let mut outdated = 0;
+        let mut failed = 0;
+        let mut changelogs = vec![];
+
+        for addon in config.addons.iter() {
+            let url = match addon.url {
+                Some(ref url) => url,
+                None => continue,
+            };
+
+            let remote = match addon_manager.get_remote_addon(url) {
+                Ok(remote) => remote,
+                Err(e) => {
+                    println!("{} Failed {}!", "☒".red(), addon.name);
+                    println!("{}", e);
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            let installed = installed_addons_list
+                .addons
+                .iter()
+                .find(|a| a.name == addon.name);
+            let locked = lock_file.get(&addon.name);
+
+            if eso_addons::is_up_to_date(addon, &remote, locked, installed) {
+                continue;
+            }
+
+            let installed_version = match (locked, installed) {
+                (_, None) => "NOT INSTALLED".truecolor(200, 200, 0).to_string(),
+                (Some(locked), Some(_)) if locked.version.is_some() => {
+                    locked.version.clone().unwrap()
+                }
+                (_, Some(installed)) => installed.manifest.version.clone().unwrap_or_default(),
+            };
+            if self.show_changelog && installed.is_some() {
+                let installed_version = eso_addons::get_installed_version(locked, installed);
+                let changes = eso_addons::get_pending_changes(addon, &remote, installed_version);
+                if !changes.is_empty() {
+                    changelogs.push((addon.name.clone(), changes.to_vec()));
+                }
+            }
+
+            let latest_version = match addon.version {
+                Some(ref pin) => format!("{} (pinned {})", remote.version.unwrap_or_default(), pin),
+                None => remote.version.unwrap_or_default(),
+            };
+
+            let info_url = remote
+                .file_id
+                .map(|file_id| htmlparser::get_info_url(addon_manager.client().base_url(), file_id))
+                .unwrap_or_else(|| url.to_owned());
+
+            table.add_row(row![
+                addon.name,
+                installed_version,
+                latest_version.green(),
+                remote.updated.unwrap_or_default(),
+                info_url
+            ]);
+            outdated += 1;
+        }
+
+        if outdated > 0 {
+            table.printstd();
+        }
+
+        for (name, changes) in changelogs {
+            println!();
+            println!("{}", name.bold());
+            changelog::print_changes(&changes);
+        }
+
+        // a failed lookup can hide an update, so it isn't reported as up to date
+        if failed > 0 {
+            return Err(Error::UpdateCheckFailed(failed));
+        }
+        if outdated > 0 {
+            return Err(Error::UpdatesAvailable(outdated));
+        }
+
+        println!("All addons are up to date");
+        Ok(())
+    }
+}
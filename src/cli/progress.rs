@@ -0,0 +1,137 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use eso_addons::progress::Transfer;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// Progress bars for the downloads of several addons and an overall bar.
+/// Nothing is drawn, if stdout is not a terminal.
+pub struct DownloadProgress {
+    bars: MultiProgress,
+    overall: ProgressBar,
+    downloaded: AtomicU64,
+}
+
+impl DownloadProgress {
+    pub fn new(addons: usize) -> DownloadProgress {
+        let target = if std::io::stdout().is_terminal() {
+            ProgressDrawTarget::stdout()
+        } else {
+            ProgressDrawTarget::hidden()
+        };
+        let bars = MultiProgress::with_draw_target(target);
+
+        let overall = bars.add(ProgressBar::new(addons as u64));
+        overall.set_style(
+            ProgressStyle::with_template("{bar:30.green/white} {pos}/{len} addons, {msg}")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        overall.set_message(format!("{} downloaded", HumanBytes(0)));
+
+        DownloadProgress {
+            bars,
+            overall,
+            downloaded: AtomicU64::new(0),
+        }
+    }
+
+    /// Callback showing the download of `name` with bytes, rate and ETA.
+    pub fn transfer<'a>(&'a self, name: &str) -> impl Fn(Transfer) + 'a {
+        let name = name.to_owned();
+        let bar: Mutex<Option<ProgressBar>> = Mutex::new(None);
+        let reported = AtomicU64::new(0);
+
+        move |transfer| match transfer {
+            Transfer::Started { total } => {
+                let new_bar = match total {
+                    Some(total) => ProgressBar::new(total).with_style(
+                        ProgressStyle::with_template(
+                            "{prefix:.bold} {bar:30} {bytes}/{total_bytes} {bytes_per_sec}, ETA {eta}",
+                        )
+                        .unwrap()
+                        .progress_chars("=> "),
+                    ),
+                    None => ProgressBar::new_spinner().with_style(
+                        ProgressStyle::with_template("{prefix:.bold} {spinner} {bytes} {bytes_per_sec}")
+                            .unwrap(),
+                    ),
+                };
+                new_bar.set_prefix(name.clone());
+
+                // a retried download starts over, so do its bytes
+                let mut bar = bar.lock().unwrap();
+                if let Some(previous) = bar.take() {
+                    self.bars.remove(&previous);
+                }
+                self.remove_downloaded(reported.swap(0, Ordering::Relaxed));
+                *bar = Some(self.bars.insert_before(&self.overall, new_bar));
+            }
+            Transfer::Progress { downloaded, .. } => {
+                if let Some(bar) = bar.lock().unwrap().as_ref() {
+                    bar.set_position(downloaded);
+                }
+                let delta = downloaded - reported.swap(downloaded, Ordering::Relaxed);
+                self.add_downloaded(delta);
+            }
+            Transfer::Finished { .. } => {
+                if let Some(bar) = bar.lock().unwrap().take() {
+                    bar.finish_and_clear();
+                    self.bars.remove(&bar);
+                }
+            }
+        }
+    }
+
+    /// Counts an addon as done in the overall bar.
+    pub fn inc(&self) {
+        self.overall.inc(1);
+    }
+
+    pub fn finish(&self) {
+        self.overall.finish_and_clear();
+        let _ = self.bars.clear();
+    }
+
+    fn add_downloaded(&self, bytes: u64) {
+        let downloaded = self.downloaded.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.overall
+            .set_message(format!("{} downloaded", HumanBytes(downloaded)));
+    }
+
+    fn remove_downloaded(&self, bytes: u64) {
+        let downloaded = self.downloaded.fetch_sub(bytes, Ordering::Relaxed) - bytes;
+        self.overall
+            .set_message(format!("{} downloaded", HumanBytes(downloaded)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_retried_transfer_is_counted_once() {
+        let progress = DownloadProgress::new(1);
+        let transfer = progress.transfer("LibDebugLogger");
+
+        transfer(Transfer::Started { total: Some(100) });
+        transfer(Transfer::Progress {
+            downloaded: 60,
+            total: Some(100),
+        });
+        transfer(Transfer::Started { total: Some(100) });
+        transfer(Transfer::Progress {
+            downloaded: 100,
+            total: Some(100),
+        });
+        transfer(Transfer::Finished {
+            downloaded: 100,
+            elapsed: Duration::from_secs(1),
+        });
+
+        assert_eq!(progress.downloaded.load(Ordering::Relaxed), 100);
+    }
+}
@@ -5,12 +5,15 @@ use colored::*;
 use eso_addons::{
     addons::Manager,
     config::{self, Config},
+    graph::DependencyGraph,
+    lock,
 };
 
 use super::{Error, Result};
 
 #[derive(Parser)]
 pub struct RemoveCommand {
+    #[clap(value_parser)]
     name: Option<String>,
 }
 
@@ -36,14 +39,55 @@ impl RemoveCommand {
 
         let entry = config.addons.remove(idx);
 
-        let addon = addon_manager.get_addon(&entry.name)?;
-        if let Some(addon) = addon {
-            addon_manager.delete_addon(&addon)?;
-        }
+        let installed_addons_list = addon_manager.get_addons()?;
+        let graph = DependencyGraph::new(&installed_addons_list.addons);
+
+        let lock_filepath = lock::get_lock_file_path(config_filepath);
+        let mut lock_file = lock::parse_lock_file(&lock_filepath)?;
+        let locked = lock_file.remove(&entry.name);
+
+        let folders = if !entry.folders.is_empty() {
+            entry.folders.clone()
+        } else if let Some(ref locked) = locked {
+            locked.folders.clone()
+        } else {
+            vec![entry.name.clone()]
+        };
+        // folders shared with other addons from the config file are kept
+        let folders: Vec<String> = folders
+            .into_iter()
+            .filter(|folder| {
+                !config
+                    .addons
+                    .iter()
+                    .any(|other| &other.name == folder || other.folders.contains(folder))
+                    && !lock_file
+                        .addons
+                        .iter()
+                        .any(|other| other.folders.contains(folder))
+            })
+            .collect();
+        addon_manager.delete_folders(&folders)?;
 
         config::save_config(config_filepath, config)?;
+        if locked.is_some() {
+            lock::save_lock_file(&lock_filepath, &lock_file)?;
+        }
 
         println!("{} Uninstalled {}!", "✔".green(), &entry.name);
+        for folder in folders.iter().filter(|folder| **folder != entry.name) {
+            let msg = format!("  - removed {}", folder);
+            println!("{}", msg.truecolor(130, 130, 130));
+        }
+
+        let needed_by: Vec<&str> = graph
+            .dependents(&entry.name)
+            .iter()
+            .map(|edge| edge.from.as_str())
+            .collect();
+        if !needed_by.is_empty() {
+            println!("⚠ {} was needed by: {}", &entry.name, needed_by.join(", "));
+        }
 
         Ok(())
     }
@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use colored::*;
+use eso_addons::{
+    addons::Manager,
+    config::Config,
+    errors::Error as AppError,
+    htmlparser::{self, SearchResult},
+};
+
+use super::add::AddCommand;
+use super::{new_table, Error, Result};
+
+#[derive(Parser)]
+pub struct SearchCommand {
+    #[clap(value_parser, required = true)]
+    query: Vec<String>,
+    #[clap(
+        short,
+        long,
+        action,
+        help = "Only list the matches, don't ask for an addon to install"
+    )]
+    list: bool,
+    #[clap(
+        short,
+        long,
+        action,
+        help = "Indicate, if the addon is only a dependency for another addon"
+    )]
+    dependency: bool,
+    #[clap(long, action, help = "Don't install missing dependencies")]
+    no_deps: bool,
+    #[clap(
+        short,
+        long,
+        action,
+        help = "Install missing dependencies without asking"
+    )]
+    yes: bool,
+}
+
+impl SearchCommand {
+    pub fn run(
+        &self,
+        cfg: &mut Config,
+        config_filepath: &Path,
+        addon_manager: &Manager,
+    ) -> Result<()> {
+        let query = self.query.join(" ");
+        if addon_manager.is_offline() {
+            let url = htmlparser::get_search_url(addon_manager.client().base_url(), &query);
+            return Err(AppError::Offline(url).into());
+        }
+
+        let results = htmlparser::search_addons(addon_manager.client(), &query)?;
+        if results.is_empty() {
+            println!("No addons found for {}", query);
+            return Ok(());
+        }
+
+        print_results(&results);
+
+        if self.list {
+            return Ok(());
+        }
+
+        let result = match self.ask_for_addon(&results)? {
+            Some(result) => result,
+            None => return Ok(()),
+        };
+
+        AddCommand::with_url(&result.url, self.dependency, self.no_deps, self.yes).run(
+            cfg,
+            config_filepath,
+            addon_manager,
+        )
+    }
+
+    fn ask_for_addon<'a>(&self, results: &'a [SearchResult]) -> Result<Option<&'a SearchResult>> {
+        let mut choices: Vec<String> = results
+            .iter()
+            .map(|result| match result.author {
+                Some(ref author) => format!("{} by {}", result.name, author),
+                None => result.name.clone(),
+            })
+            .collect();
+        choices.push("Cancel".to_owned());
+
+        let question = requestty::Question::select("addon")
+            .message("Select addon to install")
+            .choices(choices)
+            .build();
+
+        let answer = requestty::prompt_one(question).map_err(|err| Error::Other(Box::new(err)))?;
+        let index = answer.as_list_item().map(|item| item.index);
+
+        Ok(index.and_then(|index| results.get(index)))
+    }
+}
+
+fn print_results(results: &[SearchResult]) {
+    let mut table = new_table();
+    table.set_titles(row![
+        "Name".bold(),
+        "Author".bold(),
+        "Downloads".bold(),
+        "Updated".bold(),
+        "Compatibility".bold()
+    ]);
+
+    for result in results {
+        table.add_row(row![
+            result.name,
+            result.author.clone().unwrap_or_default(),
+            r->result.downloads.map(format_count).unwrap_or_default(),
+            result.updated.clone().unwrap_or_default(),
+            result.compatibility.clone().unwrap_or_default()
+        ]);
+    }
+
+    table.printstd();
+}
+
+/// Formats a number with thousands separators like esoui.com.
+fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1024), "1,024");
+        assert_eq!(format_count(4210633), "4,210,633");
+    }
+}
@@ -1,33 +1,164 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
 use colored::*;
-use eso_addons::{addons::Manager, config::Config};
+use eso_addons::{
+    addons::{Addon, FetchedArchive, Manager},
+    config::{self, AddonEntry, Config},
+    htmlparser::ChangelogEntry,
+    lock::{self, LockFile, LockedAddon},
+    progress::Transfer,
+};
 
+use super::changelog;
+use super::dependencies;
 use super::errors::*;
+use super::progress::DownloadProgress;
 
 #[derive(Parser)]
-pub struct UpdateCommand {}
+pub struct UpdateCommand {
+    #[clap(long, action, help = "Don't install missing dependencies")]
+    no_deps: bool,
+    #[clap(
+        short,
+        long,
+        action,
+        help = "Install missing dependencies without asking"
+    )]
+    yes: bool,
+    #[clap(
+        long,
+        action,
+        alias = "frozen",
+        help = "Install exactly the versions from the lock file"
+    )]
+    locked: bool,
+    #[clap(long, action, help = "Download addons even if they are up to date")]
+    force: bool,
+    #[clap(
+        short,
+        long,
+        value_parser = clap::value_parser!(u16).range(1..),
+        default_value_t = 4,
+        help = "Number of concurrent downloads"
+    )]
+    jobs: u16,
+    #[clap(long, action, help = "Show the changelog of updated addons")]
+    show_changelog: bool,
+}
 
 impl UpdateCommand {
-    pub fn run(&self, config: &Config, addon_manager: &Manager) -> Result<()> {
-        let desired_addons = &config.addons;
+    pub fn run(
+        &self,
+        config: &mut Config,
+        config_filepath: &Path,
+        addon_manager: &Manager,
+    ) -> Result<()> {
+        let lock_filepath = lock::get_lock_file_path(config_filepath);
+        let mut lock_file = lock::parse_lock_file(&lock_filepath)?;
+        let previously_installed = addon_manager.get_addons()?.addons;
+
+        if self.locked {
+            if let Some(addon) = config
+                .addons
+                .iter()
+                .find(|addon| addon.url.is_some() && lock_file.get(&addon.name).is_none())
+            {
+                return Err(Error::NotLocked(addon.name.clone()));
+            }
+        }
+
+        // archives are downloaded concurrently, but installed one after
+        // another in the order of the config file
+        let progress = DownloadProgress::new(config.addons.len());
+        let fetched = run_parallel(&config.addons, self.jobs, |addon| {
+            let result = self.fetch_addon(
+                addon,
+                addon_manager,
+                &lock_file,
+                &previously_installed,
+                &progress.transfer(&addon.name),
+            );
+            progress.inc();
+            result
+        });
+        progress.finish();
+
+        let mut updated = 0;
+        let mut up_to_date = 0;
+        let mut failed = 0;
+
+        for (addon, fetched) in config.addons.iter_mut().zip(fetched) {
+            let mut removed_files = vec![];
+            let mut changes = vec![];
 
-        for addon in desired_addons.iter() {
-            let installed = if let Some(ref url) = addon.url {
-                let installed = match addon_manager.download_addon(&url) {
-                    Ok(installed) => installed,
+            let installed = if addon.url.is_some() {
+                let result = fetched.and_then(|archive| match archive {
+                    Some((archive, pending)) => {
+                        changes = pending;
+                        addon_manager.install_archive(archive).map(Some)
+                    }
+                    None => Ok(None),
+                });
+
+                let download = match result {
+                    Ok(Some(download)) => download,
+                    Ok(None) => {
+                        println!(
+                            "{} {}",
+                            "✔".green(),
+                            format!("{} is up to date", addon.name).truecolor(130, 130, 130)
+                        );
+                        up_to_date += 1;
+                        continue;
+                    }
+                    Err(e) if self.locked => {
+                        println!("{} Failed {}!", "☒".red(), addon.name);
+                        return Err(e.into());
+                    }
                     Err(e) => {
                         println!("{} Failed {}!", "☒".red(), addon.name);
-                        println!("{}", e.to_string());
+                        println!("{}", e);
+                        failed += 1;
                         continue;
                     }
                 };
-                Some(installed)
+                updated += 1;
+
+                if !self.locked {
+                    let stale_folders = lock_file.get_stale_folders(&addon.name, &download.folders);
+                    match addon_manager.delete_folders(&stale_folders) {
+                        Ok(files) => removed_files.extend(files),
+                        Err(e) => println!("⚠ {}", e),
+                    }
+
+                    lock_file.insert(LockedAddon::new(&addon.name, &download));
+                    addon.folders = download.folders;
+                }
+                removed_files.extend(download.removed_files);
+
+                Some(download.addon)
             } else {
                 addon_manager.get_addon(&addon.name)?
             };
 
             if let Some(installed) = installed {
                 if installed.name == addon.name {
-                    println!("{} Updated {}!", "✔".green(), addon.name);
+                    match installed.manifest.version {
+                        Some(ref version) => {
+                            println!("{} Updated {} ({})!", "✔".green(), addon.name, version)
+                        }
+                        None => println!("{} Updated {}!", "✔".green(), addon.name),
+                    }
+
+                    changelog::print_changes(&changes);
+
+                    for file in removed_files {
+                        let msg = format!("  - removed {}", file.display());
+                        println!("{}", msg.truecolor(130, 130, 130));
+                    }
                 } else {
                     println!(
                         // TODO: change the name in the config automatically
@@ -43,11 +174,30 @@ impl UpdateCommand {
             }
         }
 
+        if !self.locked {
+            config::save_config(config_filepath, config)?;
+            lock::save_lock_file(&lock_filepath, &lock_file)?;
+        }
+
+        println!(
+            "\n{} updated, {} up to date, {} failed",
+            updated, up_to_date, failed
+        );
+
+        if !self.no_deps && !self.locked && !addon_manager.is_offline() {
+            dependencies::install_missing_dependencies(
+                config,
+                config_filepath,
+                addon_manager,
+                self.yes,
+            )?;
+        }
+
         let installed_addons_list = addon_manager.get_addons()?;
         let missing_addons: Vec<String> =
             eso_addons::get_missing_dependencies(&installed_addons_list.addons).collect();
 
-        if missing_addons.len() > 0 {
+        if !missing_addons.is_empty() {
             println!(
                 "\n{} There are missing dependencies! Please install the following addons to resolve the dependencies:",
                 "⚠".red()
@@ -58,10 +208,40 @@ impl UpdateCommand {
             }
         }
 
+        let optional_addons =
+            eso_addons::get_missing_optional_dependencies(&installed_addons_list.addons);
+
+        if !optional_addons.is_empty() {
+            println!("\nThere are optional dependencies, which are not installed:");
+
+            for optional in optional_addons {
+                println!("- {}", optional);
+            }
+        }
+
+        let outdated_addons = eso_addons::get_outdated_dependencies(&installed_addons_list.addons);
+
+        if !outdated_addons.is_empty() {
+            println!(
+                "\n{} There are outdated dependencies! Please update the following addons:",
+                "⚠".red()
+            );
+
+            for outdated in outdated_addons {
+                match outdated.installed_version {
+                    Some(version) => println!(
+                        "- {} (installed {}, needs {})",
+                        outdated.name, version, outdated.required
+                    ),
+                    None => println!("- {} (needs {})", outdated.name, outdated.required),
+                }
+            }
+        }
+
         let unused_addons =
-            eso_addons::get_unused_dependencies(&installed_addons_list.addons, desired_addons);
+            eso_addons::get_unused_dependencies(&installed_addons_list.addons, &config.addons);
 
-        if unused_addons.len() > 0 {
+        if !unused_addons.is_empty() {
             println!("\nThere are unused dependencies:");
 
             for unused in unused_addons {
@@ -71,4 +251,107 @@ impl UpdateCommand {
 
         Ok(())
     }
+
+    /// Downloads the archive of an addon, unless it is up to date. With
+    /// `--show-changelog`, the changes since the installed version are
+    /// returned along with it.
+    fn fetch_addon(
+        &self,
+        addon: &AddonEntry,
+        addon_manager: &Manager,
+        lock_file: &LockFile,
+        previously_installed: &[Addon],
+        on_transfer: &dyn Fn(Transfer),
+    ) -> eso_addons::errors::Result<Option<(FetchedArchive, Vec<ChangelogEntry>)>> {
+        let url = match addon.url {
+            Some(ref url) => url,
+            None => return Ok(None),
+        };
+
+        if self.locked {
+            let locked = lock_file.get(&addon.name).unwrap();
+            return addon_manager
+                .fetch_locked_addon(locked, on_transfer)
+                .map(|archive| Some((archive, vec![])));
+        }
+
+        if addon_manager.is_offline() {
+            return match lock_file.get(&addon.name) {
+                Some(locked) => addon_manager.fetch_locked_addon(locked, on_transfer),
+                None => {
+                    addon_manager.fetch_cached_addon(&addon.name, url, addon.version.as_deref())
+                }
+            }
+            .map(|archive| Some((archive, vec![])));
+        }
+
+        let remote = addon_manager.get_remote_addon(url)?;
+        let installed = previously_installed.iter().find(|a| a.name == addon.name);
+        let locked = lock_file.get(&addon.name);
+
+        if !self.force && eso_addons::is_up_to_date(addon, &remote, locked, installed) {
+            return Ok(None);
+        }
+
+        // a fresh install has no changes to show
+        let changes = if self.show_changelog && installed.is_some() {
+            let installed_version = eso_addons::get_installed_version(locked, installed);
+            eso_addons::get_pending_changes(addon, &remote, installed_version).to_vec()
+        } else {
+            vec![]
+        };
+
+        addon_manager
+            .fetch_remote_addon(&remote, addon.version.as_deref(), on_transfer)
+            .map(|archive| Some((archive, changes)))
+    }
+}
+
+/// Calls `f` for every item on up to `jobs` threads. The results are in the
+/// order of the items.
+fn run_parallel<T, R, F>(items: &[T], jobs: u16, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..(jobs as usize).min(items.len()) {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::SeqCst);
+                if idx >= items.len() {
+                    break;
+                }
+
+                let result = f(&items[idx]);
+                results.lock().unwrap()[idx] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_parallel() {
+        let items: Vec<u32> = (0..20).collect();
+
+        assert_eq!(
+            run_parallel(&items, 3, |item| item * 2),
+            (0..20).map(|item| item * 2).collect::<Vec<u32>>()
+        );
+        assert!(run_parallel(&[] as &[u32], 3, |item| *item).is_empty());
+    }
 }
@@ -0,0 +1,55 @@
+use colored::*;
+use eso_addons::addons::Manager;
+use eso_addons::config::Config;
+use eso_addons::graph::{DependencyGraph, DependencyKind};
+
+use super::{Error, Result};
+
+#[derive(Parser)]
+pub struct WhyCommand {
+    #[clap(value_parser)]
+    name: String,
+}
+
+impl WhyCommand {
+    pub fn run(&self, addon_manager: &Manager, config: &Config) -> Result<()> {
+        let installed_addons_list = addon_manager.get_addons()?;
+        let graph = DependencyGraph::new(&installed_addons_list.addons);
+
+        let configured = config.addons.iter().any(|entry| entry.manages(&self.name));
+        if !graph.contains(&self.name) && !configured {
+            return Err(Error::AddonNotFound(self.name.clone()));
+        }
+
+        let dependents = graph.all_dependents(&self.name);
+
+        if dependents.is_empty() {
+            println!("No addon depends on {}", self.name);
+            return Ok(());
+        }
+
+        println!("{} is needed by:", self.name.bold());
+
+        for dependent in dependents {
+            let mut line = format!("- {}", dependent.name);
+
+            if !dependent.path.is_empty() {
+                line.push_str(&format!(" (via {})", dependent.path.join(" → ")));
+            }
+            if dependent.kind == DependencyKind::Optional {
+                line.push_str(&format!(" {}", "optional".truecolor(130, 130, 130)));
+            }
+            if !config
+                .addons
+                .iter()
+                .any(|entry| entry.manages(&dependent.name))
+            {
+                line.push_str(&format!(" {}", "not managed".yellow()));
+            }
+
+            println!("{}", line);
+        }
+
+        Ok(())
+    }
+}
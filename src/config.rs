@@ -1,4 +1,6 @@
 use super::errors::*;
+use super::extractor::ExtractLimits;
+use super::http::HttpSettings;
 use serde::ser::SerializeStruct;
 use serde_derive::{Deserialize, Serialize};
 use std::fs;
@@ -10,6 +12,19 @@ pub struct AddonEntry {
     pub url: Option<String>,
     #[serde(default = "default_dependency")]
     pub dependency: bool,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Top-level folders installed from the addon's archive.
+    #[serde(default)]
+    pub folders: Vec<String>,
+}
+
+impl AddonEntry {
+    /// Checks, if the addon folder `name` is this addon or was installed
+    /// from its archive.
+    pub fn manages(&self, name: &str) -> bool {
+        self.name == name || self.folders.iter().any(|folder| folder == name)
+    }
 }
 
 fn default_dependency() -> bool {
@@ -21,6 +36,10 @@ fn default_dependency() -> bool {
 pub struct Config {
     #[serde(rename = "addonDir")]
     pub addon_dir: PathBuf,
+    #[serde(default, skip_serializing_if = "HttpSettings::is_default")]
+    pub http: HttpSettings,
+    #[serde(default, skip_serializing_if = "ExtractLimits::is_default")]
+    pub extract: ExtractLimits,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub addons: Vec<AddonEntry>,
 }
@@ -34,6 +53,12 @@ impl serde::Serialize for AddonEntry {
         state.serialize_field("name", &self.name)?;
         state.serialize_field("url", &self.url)?;
         state.serialize_field("dependency", &self.dependency)?;
+        state.serialize_field("version", &self.version)?;
+        if !self.folders.is_empty() {
+            state.serialize_field("folders", &self.folders)?;
+        } else {
+            state.skip_field("folders")?;
+        }
 
         state.end()
     }
@@ -67,7 +92,9 @@ fn get_initial_config() -> Config {
     let addon_dir = home_dir.join("Documents/Elder Scrolls Online/live/AddOns");
 
     Config {
-        addon_dir: addon_dir,
+        addon_dir,
+        http: HttpSettings::default(),
+        extract: ExtractLimits::default(),
         addons: vec![],
     }
 }
@@ -79,7 +106,9 @@ fn get_initial_config() -> Config {
         home_dir.join("drive_c/users/user/My Documents/Elder Scrolls Online/live/AddOns");
 
     Config {
-        addon_dir: addon_dir,
+        addon_dir,
+        http: HttpSettings::default(),
+        extract: ExtractLimits::default(),
         addons: vec![],
     }
 }
@@ -88,6 +117,8 @@ fn get_initial_config() -> Config {
 fn get_initial_config() -> Config {
     Config {
         addon_dir: PathBuf::new(),
+        http: HttpSettings::default(),
+        extract: ExtractLimits::default(),
         addons: vec![],
     }
 }
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::addons::Addon;
+use crate::graph::DependencyGraph;
+
+/// Several folders providing an addon with the same name, e.g. a library
+/// bundled inside another addon next to the standalone version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateAddon {
+    pub name: String,
+    /// The copy the game loads.
+    pub loaded: PathBuf,
+    /// The copies the game ignores.
+    pub shadowed: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    Cycle(Vec<String>),
+    DuplicateAddon(DuplicateAddon),
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            // the members are sorted by name, not in the order of the edges
+            Diagnostic::Cycle(names) => {
+                f.write_str(&format!("dependency cycle between: {}", names.join(", ")))
+            }
+            Diagnostic::DuplicateAddon(duplicate) => {
+                let shadowed: Vec<String> = duplicate
+                    .shadowed
+                    .iter()
+                    .map(|path| format!("{:?}", path))
+                    .collect();
+                f.write_str(&format!(
+                    "{} is installed multiple times, loading {:?} and ignoring {}",
+                    duplicate.name,
+                    duplicate.loaded,
+                    shadowed.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+/// Picks the copies of the addons, which the game would load. If an addon
+/// is installed multiple times, the copy with the highest `## AddOnVersion`
+/// wins. On a tie the least nested copy is loaded.
+pub fn get_loaded_addons(installed: &[Addon]) -> Vec<&Addon> {
+    group_by_name(installed)
+        .into_values()
+        .map(|mut copies| copies.remove(0))
+        .collect()
+}
+
+pub fn get_duplicate_addons(installed: &[Addon]) -> Vec<DuplicateAddon> {
+    group_by_name(installed)
+        .into_iter()
+        .filter(|(_, copies)| copies.len() > 1)
+        .map(|(name, copies)| DuplicateAddon {
+            name,
+            loaded: copies[0].path.clone(),
+            shadowed: copies[1..].iter().map(|a| a.path.clone()).collect(),
+        })
+        .collect()
+}
+
+pub fn diagnose(installed: &[Addon]) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = DependencyGraph::new(installed)
+        .cycles()
+        .into_iter()
+        .map(Diagnostic::Cycle)
+        .collect();
+
+    diagnostics.extend(
+        get_duplicate_addons(installed)
+            .into_iter()
+            .map(Diagnostic::DuplicateAddon),
+    );
+
+    diagnostics
+}
+
+// Copies are sorted by load priority, the loaded copy first.
+fn group_by_name(installed: &[Addon]) -> BTreeMap<String, Vec<&Addon>> {
+    let mut groups: BTreeMap<String, Vec<&Addon>> = BTreeMap::new();
+
+    for addon in installed.iter() {
+        groups.entry(addon.name.clone()).or_default().push(addon);
+    }
+
+    for copies in groups.values_mut() {
+        copies.sort_by(|a, b| {
+            b.manifest
+                .addon_version
+                .unwrap_or(0)
+                .cmp(&a.manifest.addon_version.unwrap_or(0))
+                .then(
+                    a.path
+                        .components()
+                        .count()
+                        .cmp(&b.path.components().count()),
+                )
+                .then(a.path.cmp(&b.path))
+        });
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn addon(path: &str, addon_version: Option<u32>) -> Addon {
+        let mut addon = testing::addon(path, &[], &[]);
+        addon.manifest.addon_version = addon_version;
+        addon
+    }
+
+    #[test]
+    fn test_duplicate_addons() {
+        let installed = vec![
+            addon("AddOns/CombatMetrics/Libs/LibAddonMenu-2.0", Some(32)),
+            addon("AddOns/LibAddonMenu-2.0", Some(32)),
+            addon("AddOns/Bundled/LibAddonMenu-2.0", Some(30)),
+            addon("AddOns/Other/LibDebugLogger", Some(100)),
+            addon("AddOns/LibDebugLogger", Some(90)),
+            addon("AddOns/CombatMetrics", None),
+        ];
+
+        assert_eq!(
+            get_duplicate_addons(&installed),
+            vec![
+                DuplicateAddon {
+                    name: "LibAddonMenu-2.0".to_owned(),
+                    loaded: PathBuf::from("AddOns/LibAddonMenu-2.0"),
+                    shadowed: vec![
+                        PathBuf::from("AddOns/CombatMetrics/Libs/LibAddonMenu-2.0"),
+                        PathBuf::from("AddOns/Bundled/LibAddonMenu-2.0"),
+                    ],
+                },
+                DuplicateAddon {
+                    name: "LibDebugLogger".to_owned(),
+                    loaded: PathBuf::from("AddOns/Other/LibDebugLogger"),
+                    shadowed: vec![PathBuf::from("AddOns/LibDebugLogger")],
+                },
+            ]
+        );
+
+        let loaded: Vec<&PathBuf> = get_loaded_addons(&installed)
+            .into_iter()
+            .map(|a| &a.path)
+            .collect();
+        assert_eq!(
+            loaded,
+            vec![
+                &PathBuf::from("AddOns/CombatMetrics"),
+                &PathBuf::from("AddOns/LibAddonMenu-2.0"),
+                &PathBuf::from("AddOns/Other/LibDebugLogger"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagnose_cycle() {
+        let installed = vec![
+            testing::addon("A", &["C"], &[]),
+            testing::addon("B", &["A"], &[]),
+            testing::addon("C", &["B"], &[]),
+        ];
+
+        let diagnostics = diagnose(&installed);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::Cycle(vec![
+                "A".to_owned(),
+                "B".to_owned(),
+                "C".to_owned()
+            ])]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "dependency cycle between: A, B, C"
+        );
+    }
+}
@@ -1,13 +1,31 @@
+use crate::extractor::ExtractError;
 use std::{error, path::PathBuf};
 
 #[derive(Debug)]
 pub enum Error {
-    CannotOpenAddonDirectory(PathBuf, Box<dyn error::Error>),
-    CannotRemoveAddon(String, Box<dyn error::Error>),
+    CannotOpenAddonDirectory(PathBuf, Box<dyn error::Error + Send + Sync>),
+    CannotRemoveAddon(String, Box<dyn error::Error + Send + Sync>),
     CannotLoadConfig,
-    CannotDownloadAddon(String, Box<dyn error::Error>),
-    CannotReadAddon(String, Box<dyn error::Error>),
-    Other(Box<dyn error::Error>),
+    CannotDownloadAddon(String, Box<dyn error::Error + Send + Sync>),
+    CannotInstallAddon(String, Box<dyn error::Error + Send + Sync>),
+    CannotExtractAddon(String, ExtractError),
+    CannotReadAddon(String, Box<dyn error::Error + Send + Sync>),
+    CannotLoadLockFile(Box<dyn error::Error + Send + Sync>),
+    CannotUseCache(Box<dyn error::Error + Send + Sync>),
+    NotCached(String),
+    Offline(String),
+    VersionNotFound(String, String),
+    ChecksumMismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    PublishedChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    Other(Box<dyn error::Error + Send + Sync>),
 }
 
 impl From<std::io::Error> for Error {
@@ -29,9 +47,45 @@ impl std::fmt::Display for Error {
             Error::CannotDownloadAddon(url, err) => {
                 f.write_str(&format!("cannot download addon {}: {}", url, err))
             }
+            Error::CannotInstallAddon(url, err) => {
+                f.write_str(&format!("cannot install addon {}: {}", url, err))
+            }
+            Error::CannotExtractAddon(url, err) => {
+                f.write_str(&format!("cannot extract addon {}: {}", url, err))
+            }
             Error::CannotReadAddon(name, err) => {
                 f.write_str(&format!("cannot read addon {}: {}", name, err))
             }
+            Error::VersionNotFound(url, version) => f.write_str(&format!(
+                "version {} of addon {} not found in the file history",
+                version, url
+            )),
+            Error::CannotLoadLockFile(err) => {
+                f.write_str(&format!("cannot load lock file: {}", err))
+            }
+            Error::CannotUseCache(err) => {
+                f.write_str(&format!("cannot use download cache: {}", err))
+            }
+            Error::NotCached(name) => {
+                f.write_str(&format!("{} is not in the download cache", name))
+            }
+            Error::Offline(url) => f.write_str(&format!("cannot fetch {} in offline mode", url)),
+            Error::ChecksumMismatch {
+                name,
+                expected,
+                actual,
+            } => f.write_str(&format!(
+                "checksum mismatch for {}: expected {}, got {}",
+                name, expected, actual
+            )),
+            Error::PublishedChecksumMismatch {
+                url,
+                expected,
+                actual,
+            } => f.write_str(&format!(
+                "archive of {} doesn't match the MD5 published on esoui.com: expected {}, got {}",
+                url, expected, actual
+            )),
             Error::Other(err) => err.fmt(f),
         }
     }
@@ -0,0 +1,377 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::{error, fmt};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+const MIB: u64 = 1024 * 1024;
+
+// file type bits of a unix mode
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Limits for extracting an archive from the `[extract]` section of the
+/// config file, protecting against archives which unpack to huge sizes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ExtractLimits {
+    /// Maximum total size of the extracted files in MiB.
+    #[serde(rename = "maxSize")]
+    pub max_size: u64,
+    /// Maximum number of files and directories in an archive.
+    #[serde(rename = "maxEntries")]
+    pub max_entries: usize,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        ExtractLimits {
+            max_size: 1024,
+            max_entries: 20_000,
+        }
+    }
+}
+
+impl ExtractLimits {
+    pub fn is_default(&self) -> bool {
+        self == &ExtractLimits::default()
+    }
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+    TooManyEntries { count: usize, limit: usize },
+    TooLarge { limit: u64 },
+    UnsafePath(String),
+    Symlink(String),
+    CaseCollision(String, String),
+    Zip(ZipError),
+    Io(io::Error),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::TooManyEntries { count, limit } => f.write_str(&format!(
+                "archive has {} entries, the limit is {}",
+                count, limit
+            )),
+            ExtractError::TooLarge { limit } => f.write_str(&format!(
+                "archive extracts to more than {} MiB",
+                limit / MIB
+            )),
+            ExtractError::UnsafePath(name) => {
+                f.write_str(&format!("unsafe path {:?} in archive", name))
+            }
+            ExtractError::Symlink(name) => {
+                f.write_str(&format!("symbolic link {:?} in archive", name))
+            }
+            ExtractError::CaseCollision(first, second) => f.write_str(&format!(
+                "{:?} and {:?} in archive differ only in case",
+                first, second
+            )),
+            ExtractError::Zip(err) => err.fmt(f),
+            ExtractError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ExtractError {}
+
+impl From<ZipError> for ExtractError {
+    fn from(err: ZipError) -> Self {
+        ExtractError::Zip(err)
+    }
+}
+
+impl From<io::Error> for ExtractError {
+    fn from(err: io::Error) -> Self {
+        ExtractError::Io(err)
+    }
+}
+
+/// Extracts the archive into `target` and returns the paths of its entries
+/// in archive order. All entries are checked before anything is written, the
+/// size limit is enforced again while extracting, as the sizes in the archive
+/// can be forged.
+pub fn extract<R: Read + Seek>(
+    archive: R,
+    target: &Path,
+    limits: &ExtractLimits,
+) -> Result<Vec<PathBuf>, ExtractError> {
+    let mut archive = ZipArchive::new(archive)?;
+    let max_size = limits.max_size.saturating_mul(MIB);
+
+    if archive.len() > limits.max_entries {
+        return Err(ExtractError::TooManyEntries {
+            count: archive.len(),
+            limit: limits.max_entries,
+        });
+    }
+
+    let mut entries = vec![];
+    let mut declared_size: u64 = 0;
+    let mut seen: HashMap<String, String> = HashMap::new();
+
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        let name = file.name().to_owned();
+
+        if file.unix_mode().map(|mode| mode & S_IFMT) == Some(S_IFLNK) {
+            return Err(ExtractError::Symlink(name));
+        }
+
+        let path = sanitize_path(&name).ok_or_else(|| ExtractError::UnsafePath(name.clone()))?;
+
+        // Windows and Wine treat paths case-insensitively, so the entries
+        // would overwrite each other there
+        for ancestor in path.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+            let original = ancestor.to_string_lossy().into_owned();
+            match seen.get(&original.to_lowercase()) {
+                Some(other) if other != &original => {
+                    return Err(ExtractError::CaseCollision(other.clone(), original))
+                }
+                Some(_) => {}
+                None => {
+                    seen.insert(original.to_lowercase(), original);
+                }
+            }
+        }
+
+        declared_size = declared_size.saturating_add(file.size());
+        if declared_size > max_size {
+            return Err(ExtractError::TooLarge { limit: max_size });
+        }
+
+        entries.push((path, is_dir(&name)));
+    }
+
+    let mut remaining = max_size;
+
+    for (i, (path, is_dir)) in entries.iter().enumerate() {
+        let outpath = target.join(path);
+
+        if *is_dir {
+            fs::create_dir_all(&outpath)?;
+            continue;
+        }
+
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = archive.by_index(i)?;
+        let mut outfile = File::create(&outpath)?;
+        let written = io::copy(&mut file.take(remaining.saturating_add(1)), &mut outfile)?;
+        if written > remaining {
+            return Err(ExtractError::TooLarge { limit: max_size });
+        }
+        remaining -= written;
+    }
+
+    Ok(entries.into_iter().map(|(path, _)| path).collect())
+}
+
+fn is_dir(name: &str) -> bool {
+    name.ends_with('/') || name.ends_with('\\')
+}
+
+/// Converts an entry name into a relative path. Absolute paths, parent
+/// directories and names, which are not valid on Windows, are rejected.
+fn sanitize_path(name: &str) -> Option<PathBuf> {
+    let name = name.replace('\\', "/");
+    if name.starts_with('/') {
+        return None;
+    }
+
+    let mut path = PathBuf::new();
+    for component in name.split('/') {
+        if component.is_empty() || component == "." {
+            continue;
+        }
+        if component == ".." || !is_portable_name(component) {
+            return None;
+        }
+        path.push(component);
+    }
+
+    if path.as_os_str().is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+fn is_portable_name(name: &str) -> bool {
+    // ':' also rejects drive letters like "C:"
+    let invalid_char =
+        |c: char| c.is_control() || matches!(c, ':' | '<' | '>' | '"' | '|' | '?' | '*');
+    if name.chars().any(invalid_char) || name.ends_with('.') || name.ends_with(' ') {
+        return false;
+    }
+
+    let stem = name.split('.').next().unwrap_or_default().to_uppercase();
+    let reserved = match stem.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => {
+            (stem.starts_with("COM") || stem.starts_with("LPT"))
+                && stem.len() == 4
+                && matches!(stem.as_bytes()[3], b'1'..=b'9')
+        }
+    };
+    !reserved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    const CENTRAL_HEADER: &[u8] = &[0x50, 0x4b, 0x01, 0x02];
+
+    fn zip_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        for (name, content) in files {
+            if name.ends_with('/') {
+                writer.add_directory(*name, options).unwrap();
+            } else {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(content).unwrap();
+            }
+        }
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    // Patches a field of the central directory header of the entry `index`.
+    fn patch_central_header(mut data: Vec<u8>, index: usize, offset: usize, value: u32) -> Vec<u8> {
+        let start = data
+            .windows(4)
+            .enumerate()
+            .filter(|(_, window)| *window == CENTRAL_HEADER)
+            .nth(index)
+            .map(|(pos, _)| pos)
+            .unwrap();
+        data[start + offset..start + offset + 4].copy_from_slice(&value.to_le_bytes());
+        data
+    }
+
+    fn extract_data(data: Vec<u8>, limits: &ExtractLimits) -> Result<Vec<PathBuf>, ExtractError> {
+        let dir = tempfile::tempdir().unwrap();
+        extract(Cursor::new(data), dir.path(), limits)
+    }
+
+    #[test]
+    fn test_extract() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = zip_archive(&[
+            ("Addon/", b""),
+            ("Addon/Addon.txt", b"## Title: Addon"),
+            ("Addon\\Libs\\Lib.lua", b"-- lib"),
+        ]);
+
+        let paths = extract(Cursor::new(data), dir.path(), &ExtractLimits::default()).unwrap();
+
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("Addon"),
+                PathBuf::from("Addon/Addon.txt"),
+                PathBuf::from("Addon/Libs/Lib.lua"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("Addon/Libs/Lib.lua")).unwrap(),
+            "-- lib"
+        );
+    }
+
+    #[test]
+    fn test_unsafe_paths() {
+        for name in [
+            "../Addon/evil.lua",
+            "Addon/../../evil.lua",
+            "/etc/evil.lua",
+            "\\Windows\\evil.lua",
+            "C:/Windows/evil.lua",
+            "C:evil.lua",
+            "Addon/CON.txt",
+            "Addon/file.lua:stream",
+            "Addon/trailing. /evil.lua",
+        ] {
+            let data = zip_archive(&[(name, b"")]);
+            assert!(
+                matches!(
+                    extract_data(data, &ExtractLimits::default()),
+                    Err(ExtractError::UnsafePath(_))
+                ),
+                "{} was accepted",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_symlink() {
+        let data = zip_archive(&[("Addon/Addon.txt", b""), ("Addon/link", b"/etc/passwd")]);
+        // the external attributes hold the unix mode in the upper 16 bits
+        let data = patch_central_header(data, 1, 38, (S_IFLNK | 0o777) << 16);
+
+        assert!(matches!(
+            extract_data(data, &ExtractLimits::default()),
+            Err(ExtractError::Symlink(name)) if name == "Addon/link"
+        ));
+    }
+
+    #[test]
+    fn test_case_collision() {
+        let data = zip_archive(&[("Addon/Addon.txt", b""), ("addon/Other.lua", b"")]);
+
+        assert!(matches!(
+            extract_data(data, &ExtractLimits::default()),
+            Err(ExtractError::CaseCollision(first, second)) if first == "Addon" && second == "addon"
+        ));
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = ExtractLimits {
+            max_size: 1,
+            max_entries: 2,
+        };
+
+        let data = zip_archive(&[("a", b""), ("b", b""), ("c", b"")]);
+        assert!(matches!(
+            extract_data(data, &limits),
+            Err(ExtractError::TooManyEntries { count: 3, limit: 2 })
+        ));
+
+        let zeros = vec![0; 2 * MIB as usize];
+        let data = zip_archive(&[("Addon/bomb.lua", &zeros)]);
+        assert!(matches!(
+            extract_data(data.clone(), &limits),
+            Err(ExtractError::TooLarge { .. })
+        ));
+
+        // a forged uncompressed size is caught while extracting
+        let data = patch_central_header(data, 0, 24, 10);
+        assert!(matches!(
+            extract_data(data, &limits),
+            Err(ExtractError::TooLarge { .. })
+        ));
+
+        let unlimited = ExtractLimits {
+            max_size: u64::MAX,
+            ..limits
+        };
+        let data = zip_archive(&[("Addon/Addon.txt", b"## Title: Addon")]);
+        assert!(extract_data(data, &unlimited).is_ok());
+    }
+}
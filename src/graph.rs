@@ -0,0 +1,439 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::addons::Addon;
+use crate::diagnostics;
+use crate::manifest::{Dependency, VersionConstraint};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    Required,
+    Optional,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub kind: DependencyKind,
+    pub constraint: Option<VersionConstraint>,
+}
+
+/// Dependency tree of a single addon, as returned by [`DependencyGraph::tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyTree {
+    pub name: String,
+    pub kind: DependencyKind,
+    pub constraint: Option<VersionConstraint>,
+    pub installed: bool,
+    /// Set, if the addon already appears higher up in the tree. Its
+    /// dependencies are not expanded again.
+    pub cycle: bool,
+    pub dependencies: Vec<DependencyTree>,
+}
+
+/// An addon, which depends on another one, directly or through `path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependent {
+    pub name: String,
+    /// Addons between the dependent and the dependency, closest to the dependent first.
+    pub path: Vec<String>,
+    pub kind: DependencyKind,
+}
+
+/// Graph of the dependencies between installed addons. Addons referenced in
+/// `## DependsOn` or `## OptionalDependsOn`, but not installed, are also
+/// part of the graph.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    installed: BTreeSet<String>,
+    edges: BTreeMap<String, Vec<Edge>>,
+    reverse_edges: BTreeMap<String, Vec<Edge>>,
+}
+
+impl DependencyGraph {
+    /// Builds the graph from the installed addons. If an addon is installed
+    /// multiple times, only the copy loaded by the game is used.
+    pub fn new(installed: &[Addon]) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        let installed = diagnostics::get_loaded_addons(installed);
+
+        for addon in installed.iter() {
+            graph.installed.insert(addon.name.to_owned());
+            graph.edges.entry(addon.name.to_owned()).or_default();
+            graph
+                .reverse_edges
+                .entry(addon.name.to_owned())
+                .or_default();
+        }
+
+        for addon in installed.iter() {
+            graph.add_edges(addon, addon.depends_on(), DependencyKind::Required);
+            graph.add_edges(addon, addon.optional_depends_on(), DependencyKind::Optional);
+        }
+
+        graph
+    }
+
+    fn add_edges(&mut self, addon: &Addon, dependencies: &[Dependency], kind: DependencyKind) {
+        for dependency in dependencies.iter() {
+            let edge = Edge {
+                from: addon.name.to_owned(),
+                to: dependency.name.to_owned(),
+                kind,
+                constraint: dependency.constraint,
+            };
+
+            self.edges
+                .entry(addon.name.to_owned())
+                .or_default()
+                .push(edge.clone());
+            self.edges.entry(dependency.name.to_owned()).or_default();
+            self.reverse_edges
+                .entry(dependency.name.to_owned())
+                .or_default()
+                .push(edge);
+        }
+    }
+
+    pub fn is_installed(&self, name: &str) -> bool {
+        self.installed.contains(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.edges.contains_key(name)
+    }
+
+    /// All addons in the graph, installed or only referenced, sorted by name.
+    pub fn nodes(&self) -> impl Iterator<Item = &String> {
+        self.edges.keys()
+    }
+
+    /// Direct dependencies of the addon.
+    pub fn dependencies(&self, name: &str) -> &[Edge] {
+        self.edges.get(name).map(|e| e.as_slice()).unwrap_or(&[])
+    }
+
+    /// Addons directly depending on the addon.
+    pub fn dependents(&self, name: &str) -> &[Edge] {
+        self.reverse_edges
+            .get(name)
+            .map(|e| e.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Referenced addons, which are not installed. An addon is a missing
+    /// optional dependency only, if no addon requires it.
+    pub fn missing(&self, kind: DependencyKind) -> Vec<String> {
+        self.reverse_edges
+            .iter()
+            .filter(|(name, _)| !self.is_installed(name))
+            .filter(|(_, edges)| match kind {
+                DependencyKind::Required => edges.iter().any(|edge| edge.kind == kind),
+                DependencyKind::Optional => edges.iter().all(|edge| edge.kind == kind),
+            })
+            .map(|(name, _)| name.to_owned())
+            .collect()
+    }
+
+    pub fn tree(&self, name: &str) -> Option<DependencyTree> {
+        if !self.contains(name) {
+            return None;
+        }
+
+        let mut ancestors = vec![];
+        Some(self.build_tree(name, DependencyKind::Required, None, &mut ancestors))
+    }
+
+    fn build_tree(
+        &self,
+        name: &str,
+        kind: DependencyKind,
+        constraint: Option<VersionConstraint>,
+        ancestors: &mut Vec<String>,
+    ) -> DependencyTree {
+        let cycle = ancestors.iter().any(|a| a == name);
+
+        let mut tree = DependencyTree {
+            name: name.to_owned(),
+            kind,
+            constraint,
+            installed: self.is_installed(name),
+            cycle,
+            dependencies: vec![],
+        };
+
+        if cycle {
+            return tree;
+        }
+
+        ancestors.push(name.to_owned());
+        for edge in self.dependencies(name) {
+            tree.dependencies.push(self.build_tree(
+                &edge.to,
+                edge.kind,
+                edge.constraint,
+                ancestors,
+            ));
+        }
+        ancestors.pop();
+
+        tree
+    }
+
+    /// All addons depending on the addon, directly or indirectly, in
+    /// breadth-first order. A dependent is optional, if every path to the
+    /// addon has an optional edge. Otherwise its path is a required one.
+    pub fn all_dependents(&self, name: &str) -> Vec<Dependent> {
+        let mut result: Vec<Dependent> = vec![];
+        let mut found: HashMap<String, usize> = HashMap::new();
+        let mut queue: VecDeque<(String, Vec<String>, DependencyKind)> = VecDeque::new();
+
+        queue.push_back((name.to_owned(), vec![], DependencyKind::Required));
+
+        while let Some((current, path, kind)) = queue.pop_front() {
+            for edge in self.dependents(&current) {
+                if edge.from == name {
+                    continue;
+                }
+
+                let kind = match (kind, edge.kind) {
+                    (DependencyKind::Required, DependencyKind::Required) => {
+                        DependencyKind::Required
+                    }
+                    _ => DependencyKind::Optional,
+                };
+
+                let mut dependent_path = vec![];
+                if current != name {
+                    dependent_path.push(current.to_owned());
+                }
+                dependent_path.extend(path.iter().cloned());
+
+                match found.get(&edge.from) {
+                    // a required path wins over an optional one found
+                    // earlier, its dependents are visited again to upgrade them
+                    Some(&index) => {
+                        let dependent = &mut result[index];
+                        if kind != DependencyKind::Required || dependent.kind == kind {
+                            continue;
+                        }
+                        dependent.kind = kind;
+                        dependent.path = dependent_path.clone();
+                    }
+                    None => {
+                        found.insert(edge.from.to_owned(), result.len());
+                        result.push(Dependent {
+                            name: edge.from.to_owned(),
+                            path: dependent_path.clone(),
+                            kind,
+                        });
+                    }
+                }
+                queue.push_back((edge.from.to_owned(), dependent_path, kind));
+            }
+        }
+
+        result
+    }
+
+    /// Groups of addons, which require each other in a cycle. Optional
+    /// dependencies are ignored, as they don't prevent an addon from loading.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut state = CycleSearch::default();
+
+        for name in self.edges.keys() {
+            if !state.index.contains_key(name.as_str()) {
+                self.strong_connect(name, &mut state);
+            }
+        }
+
+        state.cycles
+    }
+
+    // Tarjan's strongly connected components algorithm
+    fn strong_connect<'a>(&'a self, name: &'a str, state: &mut CycleSearch<'a>) {
+        let index = state.index.len();
+        state.index.insert(name, index);
+        state.lowlink.insert(name, index);
+        state.stack.push(name);
+        state.on_stack.insert(name);
+
+        for edge in self.dependencies(name) {
+            if edge.kind != DependencyKind::Required {
+                continue;
+            }
+
+            let to = edge.to.as_str();
+            if !state.index.contains_key(to) {
+                self.strong_connect(to, state);
+                let lowlink = state.lowlink[name].min(state.lowlink[to]);
+                state.lowlink.insert(name, lowlink);
+            } else if state.on_stack.contains(to) {
+                let lowlink = state.lowlink[name].min(state.index[to]);
+                state.lowlink.insert(name, lowlink);
+            }
+        }
+
+        if state.lowlink[name] != state.index[name] {
+            return;
+        }
+
+        let mut component = vec![];
+        while let Some(member) = state.stack.pop() {
+            state.on_stack.remove(member);
+            component.push(member.to_owned());
+            if member == name {
+                break;
+            }
+        }
+
+        let self_loop = self
+            .dependencies(name)
+            .iter()
+            .any(|edge| edge.kind == DependencyKind::Required && edge.to == name);
+        if component.len() > 1 || self_loop {
+            component.sort();
+            state.cycles.push(component);
+        }
+    }
+}
+
+#[derive(Default)]
+struct CycleSearch<'a> {
+    index: HashMap<&'a str, usize>,
+    lowlink: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    on_stack: HashSet<&'a str>,
+    cycles: Vec<Vec<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::addon;
+
+    fn installed() -> Vec<Addon> {
+        vec![
+            addon("SkyShards", &["LibMapPins-1.0"], &["LibDebugLogger"]),
+            addon("LibMapPins-1.0", &["LibGPS"], &[]),
+            addon("LibGPS", &["LibMapPing"], &[]),
+            addon("LibDebugLogger", &[], &[]),
+        ]
+    }
+
+    #[test]
+    fn test_tree() {
+        let graph = DependencyGraph::new(&installed());
+
+        let tree = graph.tree("SkyShards").unwrap();
+
+        assert_eq!(tree.dependencies.len(), 2);
+        assert_eq!(tree.dependencies[0].name, "LibMapPins-1.0");
+        assert_eq!(tree.dependencies[0].dependencies[0].name, "LibGPS");
+
+        let lib_map_ping = &tree.dependencies[0].dependencies[0].dependencies[0];
+        assert_eq!(lib_map_ping.name, "LibMapPing");
+        assert!(!lib_map_ping.installed);
+
+        assert_eq!(tree.dependencies[1].name, "LibDebugLogger");
+        assert_eq!(tree.dependencies[1].kind, DependencyKind::Optional);
+
+        assert!(graph.tree("Unknown").is_none());
+    }
+
+    #[test]
+    fn test_tree_with_cycle() {
+        let graph = DependencyGraph::new(&[addon("A", &["B"], &[]), addon("B", &["A"], &[])]);
+
+        let tree = graph.tree("A").unwrap();
+
+        assert!(!tree.cycle);
+        assert!(tree.dependencies[0].dependencies[0].cycle);
+        assert!(tree.dependencies[0].dependencies[0].dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let graph = DependencyGraph::new(&[
+            addon("A", &["B"], &[]),
+            addon("B", &["C"], &[]),
+            addon("C", &["A"], &[]),
+            addon("D", &["D"], &[]),
+            addon("E", &["A"], &["F"]),
+            addon("F", &[], &["E"]),
+        ]);
+
+        assert_eq!(
+            graph.cycles(),
+            vec![
+                vec!["A".to_owned(), "B".to_owned(), "C".to_owned()],
+                vec!["D".to_owned()],
+            ]
+        );
+        assert!(DependencyGraph::new(&installed()).cycles().is_empty());
+    }
+
+    #[test]
+    fn test_all_dependents() {
+        let graph = DependencyGraph::new(&installed());
+
+        assert_eq!(
+            graph.all_dependents("LibGPS"),
+            vec![
+                Dependent {
+                    name: "LibMapPins-1.0".to_owned(),
+                    path: vec![],
+                    kind: DependencyKind::Required,
+                },
+                Dependent {
+                    name: "SkyShards".to_owned(),
+                    path: vec!["LibMapPins-1.0".to_owned()],
+                    kind: DependencyKind::Required,
+                },
+            ]
+        );
+        assert_eq!(
+            graph.all_dependents("LibDebugLogger"),
+            vec![Dependent {
+                name: "SkyShards".to_owned(),
+                path: vec![],
+                kind: DependencyKind::Optional,
+            }]
+        );
+        assert_eq!(
+            graph.missing(DependencyKind::Required),
+            vec!["LibMapPing".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_all_dependents_prefers_required_path() {
+        let graph = DependencyGraph::new(&[
+            addon("A", &["B"], &["Lib"]),
+            addon("B", &["Lib"], &[]),
+            addon("C", &["A"], &[]),
+            addon("Lib", &[], &[]),
+        ]);
+
+        assert_eq!(
+            graph.all_dependents("Lib"),
+            vec![
+                Dependent {
+                    name: "A".to_owned(),
+                    path: vec!["B".to_owned()],
+                    kind: DependencyKind::Required,
+                },
+                Dependent {
+                    name: "B".to_owned(),
+                    path: vec![],
+                    kind: DependencyKind::Required,
+                },
+                Dependent {
+                    name: "C".to_owned(),
+                    path: vec!["A".to_owned(), "B".to_owned()],
+                    kind: DependencyKind::Required,
+                },
+            ]
+        );
+    }
+}
@@ -1,9 +1,35 @@
-use scraper::{Html, Selector};
-use std::io::Read;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
 
 use crate::errors::{Error, Result};
+use crate::http::EsouiClient;
 
-pub fn get_addon_name(doc: Html) -> Option<String> {
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedFile {
+    pub version: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub id: u32,
+    pub name: String,
+    pub url: String,
+    pub author: Option<String>,
+    pub downloads: Option<u64>,
+    pub updated: Option<String>,
+    /// Game version the addon is marked compatible with, e.g. `Gold Road (10.0.0)`.
+    pub compatibility: Option<String>,
+}
+
+/// The changes of one release from the changelog tab of an addon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangelogEntry {
+    pub version: String,
+    pub changes: Vec<String>,
+}
+
+pub fn get_addon_name(doc: &Html) -> Option<String> {
     let selector = Selector::parse("meta").unwrap();
     let mut a = doc.select(&selector);
 
@@ -11,52 +37,593 @@ pub fn get_addon_name(doc: Html) -> Option<String> {
         .map(|node| node.value().attr("content").unwrap().to_owned())
 }
 
-pub fn get_cdn_download_link(doc: Html) -> Option<String> {
+pub fn get_addon_version(doc: &Html) -> Option<String> {
+    let selector = Selector::parse("#version").unwrap();
+    let node = doc.select(&selector).next()?;
+
+    let text = node.text().collect::<String>();
+    let version = text.trim().trim_start_matches("Version:").trim();
+
+    if version.is_empty() {
+        None
+    } else {
+        Some(version.to_owned())
+    }
+}
+
+/// Returns the archived versions listed on the esoui.com addon page.
+/// Relative links are resolved against `base_url`.
+pub fn get_archived_files(doc: &Html, base_url: &str) -> Vec<ArchivedFile> {
+    let row_selector = Selector::parse("tr").unwrap();
+    let cell_selector = Selector::parse("td").unwrap();
+    let link_selector = Selector::parse("a").unwrap();
+
+    let mut files = vec![];
+
+    for row in doc.select(&row_selector) {
+        let link = row.select(&link_selector).find(|node| {
+            node.value()
+                .attr("href")
+                .map(|href| href.contains("aid="))
+                .unwrap_or(false)
+        });
+        let href = match link.and_then(|node| node.value().attr("href")) {
+            Some(href) => href,
+            None => continue,
+        };
+
+        let version = row
+            .select(&cell_selector)
+            .next()
+            .map(|cell| cell.text().collect::<String>().trim().to_owned());
+        let version = match version {
+            Some(version) if !version.is_empty() => version,
+            _ => continue,
+        };
+
+        files.push(ArchivedFile {
+            version,
+            url: absolute_url(base_url, href),
+        });
+    }
+
+    files
+}
+
+pub fn get_addon_updated(doc: &Html) -> Option<String> {
+    let selector = Selector::parse("#safe").unwrap();
+    let node = doc.select(&selector).next()?;
+
+    let text = node.text().collect::<String>();
+    let updated = text.trim().trim_start_matches("Updated:").trim();
+
+    if updated.is_empty() {
+        None
+    } else {
+        Some(updated.to_owned())
+    }
+}
+
+/// Returns the game versions the addon is marked compatible with, e.g.
+/// `Gold Road (10.0.0), Scions of Ithelia (9.3.0)`.
+pub fn get_addon_compatibility(doc: &Html) -> Option<String> {
+    let selector = Selector::parse("#patch").unwrap();
+    let node = doc.select(&selector).next()?;
+
+    let versions: Vec<&str> = node
+        .text()
+        .map(|text| text.trim())
+        .filter(|text| !text.is_empty() && !text.starts_with("Compatibility"))
+        .collect();
+
+    if versions.is_empty() {
+        None
+    } else {
+        Some(versions.join(", "))
+    }
+}
+
+/// Returns the description of the addon with one line per paragraph.
+pub fn get_addon_description(doc: &Html) -> Option<String> {
+    let selector = Selector::parse("#description").unwrap();
+    let node = doc.select(&selector).next()?;
+
+    let text = node.text().collect::<String>();
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// Returns the changelog of the addon, newest release first. Lines before the
+/// first version heading are left out.
+pub fn get_changelog(doc: &Html) -> Vec<ChangelogEntry> {
+    let selector = Selector::parse("#changelog").unwrap();
+    let node = match doc.select(&selector).next() {
+        Some(node) => node,
+        None => return vec![],
+    };
+    let heading_re =
+        Regex::new(r"^(?i:v|version\s*)?(\d+(?:\.\d+)*[a-z]?(?:\s*r\d+)?)\s*(?:[-–:(].*)?$")
+            .unwrap();
+
+    let mut text = String::new();
+    collect_lines(node, &mut text);
+
+    let mut entries: Vec<ChangelogEntry> = vec![];
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if line.is_empty() {
+            continue;
+        }
+
+        match heading_re.captures(&line) {
+            Some(captures) => entries.push(ChangelogEntry {
+                version: captures[1].to_owned(),
+                changes: vec![],
+            }),
+            None => {
+                if let Some(entry) = entries.last_mut() {
+                    entry.changes.push(line);
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+// Collects the text of the node with line breaks for `<br>` and block
+// elements, list items are prefixed with a dash.
+fn collect_lines(node: ElementRef, text: &mut String) {
+    for child in node.children() {
+        if let Some(child_text) = child.value().as_text() {
+            text.push_str(child_text);
+            continue;
+        }
+
+        let element = match ElementRef::wrap(child) {
+            Some(element) => element,
+            None => continue,
+        };
+        let name = element.value().name();
+        let is_block = matches!(
+            name,
+            "br" | "p" | "div" | "li" | "ul" | "ol" | "tr" | "h1" | "h2" | "h3" | "h4"
+        );
+
+        if is_block {
+            text.push('\n');
+        }
+        if name == "li" {
+            text.push_str("- ");
+        }
+        collect_lines(element, text);
+        if is_block {
+            text.push('\n');
+        }
+    }
+}
+
+pub fn get_cdn_download_link(doc: &Html, cdn_url: &str) -> Option<String> {
     let selector = Selector::parse("a").unwrap();
     let mut a = doc.select(&selector);
 
-    let link_node = a.find(|node| {
-        node.value()
-            .attr("href")
-            .unwrap_or("")
-            .starts_with("https://cdn.esoui.com")
-    });
+    let link_node = a.find(|node| node.value().attr("href").unwrap_or("").starts_with(cdn_url));
 
     link_node.map(|node| node.value().attr("href").unwrap().to_owned())
 }
 
-pub fn get_document(url: &str) -> Result<Html> {
-    let mut response = reqwest::blocking::get(url)
-        .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
+/// Returns the MD5 checksum published for the latest archive. Only the file
+/// details of the download box are read, as descriptions and changelogs can
+/// mention other checksums.
+pub fn get_addon_md5(doc: &Html) -> Option<String> {
+    let selector = Selector::parse(".downloadbox #filedetails").unwrap();
+    let node = doc.select(&selector).next()?;
+    let re = Regex::new(r"MD5:?\s*([0-9a-fA-F]{32})\b").unwrap();
+    let text = node.text().collect::<String>();
 
-    let mut buf = Vec::new();
+    re.captures(&text)
+        .map(|captures| captures[1].to_lowercase())
+}
 
-    response
-        .read_to_end(&mut buf)
-        .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
+fn absolute_url(base_url: &str, href: &str) -> String {
+    reqwest::Url::parse(base_url)
+        .and_then(|base| base.join("downloads/"))
+        .and_then(|base| base.join(href))
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| href.to_owned())
+}
+
+pub fn get_info_url(base_url: &str, file_id: u32) -> String {
+    format!("{}downloads/fileinfo.php?id={}", base_url, file_id)
+}
+
+pub fn get_search_url(base_url: &str, query: &str) -> String {
+    let search_url = format!("{}downloads/search.php", base_url);
+    reqwest::Url::parse_with_params(&search_url, &[("search", query)])
+        .map(|url| url.to_string())
+        .unwrap_or(search_url)
+}
+
+pub fn get_search_results(doc: &Html, base_url: &str) -> Vec<SearchResult> {
+    let selector = Selector::parse("a").unwrap();
+    let re = Regex::new(r"(?:info(\d+)-[^/]*|fileinfo\.php\?id=(\d+))$").unwrap();
+
+    let mut results: Vec<SearchResult> = vec![];
+
+    for node in doc.select(&selector) {
+        let href = node.value().attr("href").unwrap_or("");
+        let id = match re.captures(href) {
+            Some(captures) => captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .and_then(|id| id.as_str().parse::<u32>().ok()),
+            None => None,
+        };
+        let id = match id {
+            Some(id) => id,
+            None => continue,
+        };
+
+        let name = node.text().collect::<String>().trim().to_owned();
+        if name.is_empty() || results.iter().any(|r| r.id == id) {
+            continue;
+        }
+
+        let row = node
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .find(|element| element.value().name() == "tr");
+
+        let mut result = SearchResult {
+            id,
+            name,
+            url: get_info_url(base_url, id),
+            author: None,
+            downloads: None,
+            updated: None,
+            compatibility: None,
+        };
+        if let Some(row) = row {
+            fill_search_details(&mut result, row);
+        }
+        results.push(result);
+    }
+
+    results
+}
+
+// Reads the author, downloads, update date and compatibility from the
+// table row of a search result.
+fn fill_search_details(result: &mut SearchResult, row: ElementRef) {
+    let author_selector = Selector::parse("a").unwrap();
+    let cell_selector = Selector::parse("td").unwrap();
+    let date_re = Regex::new(r"^\d{2}/\d{2}/\d{2}").unwrap();
+    let compat_re = Regex::new(r"\(\d+\.\d+(\.\d+)*\)").unwrap();
+
+    result.author = row
+        .select(&author_selector)
+        .find(|node| {
+            node.value()
+                .attr("href")
+                .map(|href| href.contains("member.php"))
+                .unwrap_or(false)
+        })
+        .map(|node| node.text().collect::<String>().trim().to_owned());
+
+    for cell in row.select(&cell_selector) {
+        let text = cell.text().collect::<String>();
+        let text = text.trim();
+
+        if result.downloads.is_none() && !text.is_empty() {
+            result.downloads = text.replace(',', "").parse().ok();
+        }
+        if result.updated.is_none() && date_re.is_match(text) {
+            result.updated = Some(text.to_owned());
+        }
+        if result.compatibility.is_none() && compat_re.is_match(text) {
+            result.compatibility = Some(text.to_owned());
+        }
+    }
+}
+
+pub fn search_addons(client: &dyn EsouiClient, query: &str) -> Result<Vec<SearchResult>> {
+    let base_url = client.base_url();
+    get_document(client, &get_search_url(base_url, query))
+        .map(|doc| get_search_results(&doc, base_url))
+}
 
-    let document = Html::parse_document(&String::from_utf8_lossy(&buf));
+pub fn get_document(client: &dyn EsouiClient, url: &str) -> Result<Html> {
+    let text = client
+        .get_text(url)
+        .map_err(|err| Error::CannotDownloadAddon(url.to_owned(), Box::new(err)))?;
 
-    Ok(document)
+    Ok(Html::parse_document(&text))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use scraper::Selector;
+    use crate::http::{HttpError, ReadBody, ESOUI_CDN_URL, ESOUI_URL};
+
+    // Serves a single search page instead of esoui.com.
+    struct SearchPage;
+
+    impl EsouiClient for SearchPage {
+        fn base_url(&self) -> &str {
+            "http://localhost/esoui/"
+        }
+
+        fn cdn_url(&self) -> &str {
+            "http://localhost/cdn/"
+        }
+
+        fn get_text(&self, url: &str) -> std::result::Result<String, HttpError> {
+            assert_eq!(
+                url,
+                "http://localhost/esoui/downloads/search.php?search=LibAddonMenu"
+            );
+            Ok(r#"<a href="/downloads/info7-LibAddonMenu.html">LibAddonMenu</a>"#.to_owned())
+        }
+
+        // there are no archives on the search page
+        fn download(&self, _url: &str, _read: &mut ReadBody) -> std::result::Result<(), HttpError> {
+            Err(HttpError::Status(reqwest::StatusCode::NOT_FOUND))
+        }
+    }
 
     #[test]
-    fn test_get_document() {
-        let url = "https://example.com/";
+    fn test_get_addon_version() {
+        let doc = Html::parse_document(
+            r#"<html><body>
+            <div id="version">Version: 2.0.32 </div>
+            <div id="safe">Updated: 07/12/22 09:58 AM</div>
+            </body></html>"#,
+        );
+        assert_eq!(get_addon_version(&doc), Some("2.0.32".to_owned()));
+        assert_eq!(
+            get_addon_updated(&doc),
+            Some("07/12/22 09:58 AM".to_owned())
+        );
 
-        let doc = get_document(url);
-        assert!(doc.is_ok());
+        let doc = Html::parse_document("<html><body></body></html>");
+        assert_eq!(get_addon_version(&doc), None);
+        assert_eq!(get_addon_updated(&doc), None);
+    }
 
-        let doc = doc.unwrap();
+    #[test]
+    fn test_get_addon_details() {
+        let doc = Html::parse_document(
+            r#"<html><body>
+            <div id="patch">
+              <div class="patch"><b>Compatibility:</b></div>
+              <div class="patch"><a href="/downloads/index.php">Gold Road (10.0.0)</a></div>
+              <div class="patch">Scions of Ithelia (9.3.0)</div>
+            </div>
+            <div id="description">
+              Logs   debug messages.
+              <br>
+
+              Needs <b>LibChatMessage</b> for chat output.
+            </div>
+            </body></html>"#,
+        );
+        assert_eq!(
+            get_addon_compatibility(&doc),
+            Some("Gold Road (10.0.0), Scions of Ithelia (9.3.0)".to_owned())
+        );
+        assert_eq!(
+            get_addon_description(&doc),
+            Some("Logs debug messages.\nNeeds LibChatMessage for chat output.".to_owned())
+        );
 
-        let selector = Selector::parse("title").unwrap();
-        let title = doc.select(&selector).next().unwrap();
+        let doc = Html::parse_document("<html><body></body></html>");
+        assert_eq!(get_addon_compatibility(&doc), None);
+        assert_eq!(get_addon_description(&doc), None);
+    }
+
+    #[test]
+    fn test_get_addon_md5() {
+        let doc = Html::parse_document(
+            r#"<html><body>
+            <div id="safe">Updated: 07/12/22 09:58 AM</div>
+            <div id="description">Old release MD5: 00000000000000000000000000000000</div>
+            <div class="downloadbox">
+              <div id="filedetails">MD5: <span>9E107D9D372BB6826BD81D3542A419D6</span></div>
+            </div>
+            </body></html>"#,
+        );
+        assert_eq!(
+            get_addon_md5(&doc),
+            Some("9e107d9d372bb6826bd81d3542a419d6".to_owned())
+        );
+
+        let doc = Html::parse_document(
+            r#"<html><body>
+            <div id="description">MD5: 9E107D9D372BB6826BD81D3542A419D6</div>
+            <div class="downloadbox"><div id="filedetails">MD5: unknown</div></div>
+            </body></html>"#,
+        );
+        assert_eq!(get_addon_md5(&doc), None);
+    }
+
+    #[test]
+    fn test_get_archived_files() {
+        let doc = Html::parse_document(
+            r#"<html><body><table>
+            <tr><th>Version</th><th>File</th></tr>
+            <tr><td>2.0 r31</td><td><a href="getfile.php?id=7&aid=1001">LibAddonMenu-2.0r31.zip</a></td></tr>
+            <tr><td>2.0 r30</td><td><a href="https://www.esoui.com/downloads/getfile.php?id=7&aid=1000">LibAddonMenu-2.0r30.zip</a></td></tr>
+            <tr><td>other</td><td><a href="fileinfo.php?id=7">LibAddonMenu</a></td></tr>
+            </table></body></html>"#,
+        );
+
+        assert_eq!(
+            get_archived_files(&doc, ESOUI_URL),
+            vec![
+                ArchivedFile {
+                    version: "2.0 r31".to_owned(),
+                    url: "https://www.esoui.com/downloads/getfile.php?id=7&aid=1001".to_owned(),
+                },
+                ArchivedFile {
+                    version: "2.0 r30".to_owned(),
+                    url: "https://www.esoui.com/downloads/getfile.php?id=7&aid=1000".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_search_results() {
+        let doc = Html::parse_document(
+            r#"<html><body>
+            <a href="/downloads/info7-LibAddonMenu.html">LibAddonMenu</a>
+            <a href="/downloads/info7-LibAddonMenu.html"><img src="x.png"></a>
+            <a href="fileinfo.php?id=1302">LibMapPing</a>
+            <a href="/forums/showthread.php?t=1">Forum</a>
+            </body></html>"#,
+        );
+
+        let results = get_search_results(&doc, ESOUI_URL);
+
+        assert_eq!(
+            results,
+            vec![
+                SearchResult {
+                    id: 7,
+                    name: "LibAddonMenu".to_owned(),
+                    url: "https://www.esoui.com/downloads/fileinfo.php?id=7".to_owned(),
+                    author: None,
+                    downloads: None,
+                    updated: None,
+                    compatibility: None,
+                },
+                SearchResult {
+                    id: 1302,
+                    name: "LibMapPing".to_owned(),
+                    url: "https://www.esoui.com/downloads/fileinfo.php?id=1302".to_owned(),
+                    author: None,
+                    downloads: None,
+                    updated: None,
+                    compatibility: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_search_result_details() {
+        let doc = Html::parse_document(
+            r#"<html><body><table>
+            <tr><th>File</th><th>Category</th><th>Downloads</th><th>Updated</th><th>Compatibility</th></tr>
+            <tr>
+              <td><a href="/downloads/info7-LibAddonMenu.html">LibAddonMenu</a>
+                <div>by <a href="/forums/member.php?action=getinfo&amp;userid=1">sirinsidiator</a></div></td>
+              <td>Libraries</td>
+              <td>4,210,633</td>
+              <td>07/12/22 09:58 AM</td>
+              <td>Gold Road (10.0.0)</td>
+            </tr>
+            </table></body></html>"#,
+        );
+
+        assert_eq!(
+            get_search_results(&doc, ESOUI_URL),
+            vec![SearchResult {
+                id: 7,
+                name: "LibAddonMenu".to_owned(),
+                url: "https://www.esoui.com/downloads/fileinfo.php?id=7".to_owned(),
+                author: Some("sirinsidiator".to_owned()),
+                downloads: Some(4210633),
+                updated: Some("07/12/22 09:58 AM".to_owned()),
+                compatibility: Some("Gold Road (10.0.0)".to_owned()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_get_changelog() {
+        let doc = Html::parse_document(
+            r#"<html><body>
+            <div id="changelog">
+              Changes since the first release:<br>
+              <b>v2.0 r32 - 07/12/22</b><br>
+              - fixed   error on load<br>
+              - updated API version<br>
+              <br>
+              <b>2.0 r31:</b>
+              <ul><li>added russian translation</li><li>2 bugs fixed</li></ul>
+              Version 1.0
+            </div>
+            </body></html>"#,
+        );
+        assert_eq!(
+            get_changelog(&doc),
+            vec![
+                ChangelogEntry {
+                    version: "2.0 r32".to_owned(),
+                    changes: vec![
+                        "- fixed error on load".to_owned(),
+                        "- updated API version".to_owned()
+                    ],
+                },
+                ChangelogEntry {
+                    version: "2.0 r31".to_owned(),
+                    changes: vec![
+                        "- added russian translation".to_owned(),
+                        "- 2 bugs fixed".to_owned()
+                    ],
+                },
+                ChangelogEntry {
+                    version: "1.0".to_owned(),
+                    changes: vec![],
+                },
+            ]
+        );
+
+        let doc = Html::parse_document("<html><body></body></html>");
+        assert!(get_changelog(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_get_cdn_download_link() {
+        let doc = Html::parse_document(
+            r#"<html><body>
+            <a href="https://www.esoui.com/downloads/info7-LibAddonMenu.html">LibAddonMenu</a>
+            <a href="https://cdn.esoui.com/downloads/file7/LibAddonMenu-2.0r32.zip?1">Download</a>
+            </body></html>"#,
+        );
+
+        assert_eq!(
+            get_cdn_download_link(&doc, ESOUI_CDN_URL),
+            Some("https://cdn.esoui.com/downloads/file7/LibAddonMenu-2.0r32.zip?1".to_owned())
+        );
+        assert_eq!(get_cdn_download_link(&doc, "http://localhost/cdn/"), None);
+    }
+
+    #[test]
+    fn test_search_addons() {
+        let results = search_addons(&SearchPage, "LibAddonMenu").unwrap();
 
-        assert_eq!(title.inner_html(), "Example Domain");
+        assert_eq!(
+            results,
+            vec![SearchResult {
+                id: 7,
+                name: "LibAddonMenu".to_owned(),
+                url: "http://localhost/esoui/downloads/fileinfo.php?id=7".to_owned(),
+                author: None,
+                downloads: None,
+                updated: None,
+                compatibility: None,
+            }]
+        );
     }
 }
@@ -1,98 +1,494 @@
-use std::collections::{HashMap, HashSet};
-
-use addons::Addon;
+use addons::{Addon, RemoteAddon};
 use config::AddonEntry;
+use graph::{DependencyGraph, DependencyKind};
+use htmlparser::{ChangelogEntry, SearchResult};
+use lock::LockedAddon;
+use manifest::VersionConstraint;
+use regex::Regex;
 
 extern crate colored;
+extern crate hex;
 extern crate regex;
 extern crate requestty;
 extern crate reqwest;
 extern crate scraper;
 extern crate serde;
 extern crate serde_derive;
+extern crate sha2;
 extern crate tempfile;
 extern crate toml;
 extern crate walkdir;
 extern crate zip;
 
 pub mod addons;
+pub mod cache;
 pub mod config;
+pub mod diagnostics;
 pub mod errors;
+pub mod extractor;
+pub mod graph;
 pub mod htmlparser;
+pub mod http;
+pub mod lock;
+pub mod manifest;
+pub mod progress;
+#[cfg(test)]
+mod testing;
 
-pub fn get_missing_dependencies(installed: &Vec<Addon>) -> impl Iterator<Item = String> {
-    let mut missing = HashSet::new();
+pub fn get_missing_dependencies(installed: &[Addon]) -> impl Iterator<Item = String> {
+    DependencyGraph::new(installed)
+        .missing(DependencyKind::Required)
+        .into_iter()
+}
 
-    let mut addon_map = HashSet::new();
-    for addon in installed.iter() {
-        addon_map.insert(addon.name.clone());
-    }
+/// Returns optional dependencies, which are not installed. These don't prevent
+/// any addon from loading, so they're only informational.
+pub fn get_missing_optional_dependencies(installed: &[Addon]) -> Vec<String> {
+    DependencyGraph::new(installed).missing(DependencyKind::Optional)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutdatedDependency {
+    pub name: String,
+    pub required: VersionConstraint,
+    pub installed_version: Option<u32>,
+}
+
+/// Returns installed dependencies, whose `## AddOnVersion` doesn't satisfy
+/// the version constraint of an addon depending on them.
+pub fn get_outdated_dependencies(installed: &[Addon]) -> Vec<OutdatedDependency> {
+    let mut outdated: Vec<OutdatedDependency> = vec![];
+    let installed = diagnostics::get_loaded_addons(installed);
 
     for addon in installed.iter() {
-        for dependency in addon.depends_on.iter() {
-            if !addon_map.contains(dependency) {
-                missing.insert(dependency.to_owned());
+        for dependency in addon.depends_on().iter() {
+            let required = match dependency.constraint {
+                Some(constraint) => constraint,
+                None => continue,
+            };
+
+            let dependency_addon = match installed.iter().find(|a| a.name == dependency.name) {
+                Some(dependency_addon) => dependency_addon,
+                None => continue,
+            };
+
+            let installed_version = dependency_addon.manifest.addon_version;
+            if dependency.is_satisfied_by(installed_version) {
+                continue;
+            }
+
+            let entry = OutdatedDependency {
+                name: dependency.name.to_owned(),
+                required,
+                installed_version,
+            };
+            if !outdated.contains(&entry) {
+                outdated.push(entry);
             }
         }
     }
 
-    missing.into_iter()
+    outdated
 }
 
-pub fn get_unmanaged_addons<'a, I>(desired: &Vec<AddonEntry>, installed: I) -> Vec<&'a Addon>
+/// Installed addons, which are neither in the config file nor installed
+/// from the archive of an addon in the config file. Addons nested inside
+/// another addon's folder are left out, they are removed with their parent.
+pub fn get_unmanaged_addons<'a, I>(desired: &[AddonEntry], installed: I) -> Vec<&'a Addon>
 where
     I: Iterator<Item = &'a Addon>,
 {
-    let mut result = vec![];
+    let installed: Vec<&Addon> = installed.collect();
 
-    let mut desired_map = HashSet::new();
-    for addon in desired.iter() {
-        desired_map.insert(addon.name.clone());
-    }
+    installed
+        .iter()
+        .filter(|addon| !desired.iter().any(|entry| entry.manages(&addon.name)))
+        .filter(|addon| {
+            !installed
+                .iter()
+                .any(|other| other.path != addon.path && addon.path.starts_with(&other.path))
+        })
+        .cloned()
+        .collect()
+}
+
+pub fn get_unused_dependencies(installed: &[Addon], desired: &[AddonEntry]) -> Vec<String> {
+    let graph = DependencyGraph::new(installed);
 
-    for addon in installed {
-        if !desired_map.contains(&addon.name) {
-            result.push(addon);
+    graph
+        .nodes()
+        .filter(|name| graph.dependents(name).is_empty())
+        .filter(|name| {
+            let addon_config = desired.iter().find(|x| &&x.name == name);
+            addon_config.map(|x| x.dependency).unwrap_or(true)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Checks, if the installed addon is the release from esoui.com, so that it
+/// doesn't need to be downloaded again. The lock file is trusted first, as it
+/// records the version and date shown on esoui.com. Without it, the remote
+/// version is compared with the `## Version` of the installed addon.
+pub fn is_up_to_date(
+    entry: &AddonEntry,
+    remote: &RemoteAddon,
+    locked: Option<&LockedAddon>,
+    installed: Option<&Addon>,
+) -> bool {
+    let installed = match installed {
+        Some(installed) => installed,
+        None => return false,
+    };
+
+    let (version, updated) = match entry.version {
+        Some(ref pin) if remote.version.as_ref() != Some(pin) => (Some(pin), None),
+        _ => (remote.version.as_ref(), remote.updated.as_ref()),
+    };
+
+    if let Some(locked) = locked {
+        let compared = [
+            (version, locked.version.as_ref()),
+            (updated, locked.updated.as_ref()),
+        ];
+        let known: Vec<_> = compared
+            .iter()
+            .filter(|(remote, locked)| remote.is_some() && locked.is_some())
+            .collect();
+
+        if !known.is_empty() {
+            return known.iter().all(|(remote, locked)| remote == locked);
         }
     }
 
-    result
+    version.is_some() && version == installed.manifest.version.as_ref()
 }
 
-pub fn get_unused_dependencies(installed: &Vec<Addon>, desired: &Vec<AddonEntry>) -> Vec<String> {
-    let mut dep_graph: HashMap<String, HashSet<String>> = HashMap::new();
+/// Returns the version of the installed addon, as recorded in the lock file
+/// or else from its `## Version`.
+pub fn get_installed_version<'a>(
+    locked: Option<&'a LockedAddon>,
+    installed: Option<&'a Addon>,
+) -> Option<&'a str> {
+    locked
+        .and_then(|locked| locked.version.as_deref())
+        .or_else(|| installed.and_then(|installed| installed.manifest.version.as_deref()))
+}
 
-    for addon in installed.iter() {
-        if !dep_graph.contains_key(&addon.name) {
-            dep_graph.insert(addon.name.clone(), HashSet::new());
-        }
+/// Returns the changelog entries between the installed version and the
+/// release, which an update would install. If the installed version isn't in
+/// the changelog, all entries up to that release are returned.
+pub fn get_pending_changes<'a>(
+    entry: &AddonEntry,
+    remote: &'a RemoteAddon,
+    installed_version: Option<&str>,
+) -> &'a [ChangelogEntry] {
+    let position = |version: &str| {
+        let version = normalize_version(version);
+        remote
+            .changelog
+            .iter()
+            .position(|change| normalize_version(&change.version) == version)
+    };
 
-        for dependency in addon.depends_on.iter() {
-            match dep_graph.get_mut(dependency) {
-                Some(set) => {
-                    set.insert(addon.name.to_owned());
-                }
-                None => {
-                    let mut set = HashSet::new();
-                    set.insert(addon.name.to_owned());
-                    dep_graph.insert(dependency.to_owned(), set);
-                }
-            }
+    let start = match entry.version {
+        Some(ref pin) => position(pin).unwrap_or(0),
+        None => 0,
+    };
+    let end = installed_version
+        .and_then(position)
+        .unwrap_or(remote.changelog.len());
+
+    if start < end {
+        &remote.changelog[start..end]
+    } else {
+        &[]
+    }
+}
+
+// Authors write e.g. `v2.0 r32` in the changelog and `2.0r32` as version.
+fn normalize_version(version: &str) -> String {
+    let version = version.trim().to_lowercase();
+    let version = version
+        .strip_prefix("version")
+        .or_else(|| version.strip_prefix('v'))
+        .unwrap_or(&version);
+
+    version.split_whitespace().collect()
+}
+
+/// Normalizes an addon name for comparison, so that e.g. the `LibAddonMenu-2.0`
+/// folder matches the `LibAddonMenu` addon on esoui.com.
+pub fn normalize_addon_name(name: &str) -> String {
+    let re = Regex::new(r"[-_ ]?\d+(\.\d+)*$").unwrap();
+    let name = re.replace(name.trim(), "");
+
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Filters esoui search results down to the ones, which could provide the
+/// dependency, with exact name matches first.
+pub fn get_dependency_candidates(name: &str, results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let normalized = normalize_addon_name(name);
+
+    let (mut exact, partial): (Vec<SearchResult>, Vec<SearchResult>) = results
+        .into_iter()
+        .filter(|result| {
+            let result_name = normalize_addon_name(&result.name);
+            !result_name.is_empty()
+                && (result_name.contains(&normalized) || normalized.contains(&result_name))
+        })
+        .partition(|result| normalize_addon_name(&result.name) == normalized);
+
+    exact.extend(partial);
+    exact
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manifest::VersionOperator;
+    use testing::addon;
+
+    fn entry(name: &str, dependency: bool) -> AddonEntry {
+        AddonEntry {
+            name: name.to_owned(),
+            url: None,
+            dependency,
+            version: None,
+            folders: vec![],
         }
     }
 
-    let mut unused_addons = vec![];
+    #[test]
+    fn test_get_unmanaged_addons() {
+        let nested = addon("CombatMetrics/Libs/LibCombat", &[], &[]);
+        let installed = [
+            addon("CombatMetrics", &[], &[]),
+            addon("CombatMetricsFightData", &[], &[]),
+            nested,
+            addon("LibDebugLogger", &[], &[]),
+        ];
+        let desired = vec![AddonEntry {
+            folders: vec![
+                "CombatMetrics".to_owned(),
+                "CombatMetricsFightData".to_owned(),
+            ],
+            ..entry("CombatMetrics", false)
+        }];
 
-    for (addon, dependency_for) in dep_graph.iter() {
-        if dependency_for.len() == 0 {
-            let addon_config = desired.iter().find(|x| x.name == addon.to_owned());
-            let unused = addon_config.map(|x| x.dependency).unwrap_or(true);
+        let unmanaged: Vec<&str> = get_unmanaged_addons(&desired, installed.iter())
+            .into_iter()
+            .map(|a| a.name.as_str())
+            .collect();
+        assert_eq!(unmanaged, vec!["LibDebugLogger"]);
+    }
 
-            if unused {
-                unused_addons.push(addon.to_owned())
-            }
+    #[test]
+    fn test_get_outdated_dependencies() {
+        let required = VersionConstraint {
+            operator: VersionOperator::GreaterOrEqual,
+            version: 32,
+        };
+        let settings = |addon_version: Option<u32>| {
+            let mut settings = addon("Settings", &["LibAddonMenu-2.0"], &[]);
+            settings.manifest.depends_on[0].constraint = Some(required);
+            let mut library = addon("LibAddonMenu-2.0", &[], &[]);
+            library.manifest.addon_version = addon_version;
+            vec![settings, library]
+        };
+
+        assert!(get_outdated_dependencies(&settings(Some(32))).is_empty());
+        assert_eq!(
+            get_outdated_dependencies(&settings(Some(31))),
+            vec![OutdatedDependency {
+                name: "LibAddonMenu-2.0".to_owned(),
+                required,
+                installed_version: Some(31),
+            }]
+        );
+        // a dependency without `## AddOnVersion` is treated as version 0
+        assert_eq!(
+            get_outdated_dependencies(&settings(None)),
+            vec![OutdatedDependency {
+                name: "LibAddonMenu-2.0".to_owned(),
+                required,
+                installed_version: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_is_up_to_date() {
+        let remote = RemoteAddon {
+            url: "https://www.esoui.com/downloads/download7".to_owned(),
+            file_id: Some(7),
+            version: Some("2.0 r32".to_owned()),
+            updated: Some("07/12/22 09:58 AM".to_owned()),
+            download_link: None,
+            md5: None,
+            compatibility: None,
+            description: None,
+            changelog: vec![],
+            archived_files: vec![],
+        };
+        let locked = |version: &str, updated: Option<&str>| LockedAddon {
+            name: "LibAddonMenu-2.0".to_owned(),
+            file_id: Some(7),
+            version: Some(version.to_owned()),
+            updated: updated.map(|u| u.to_owned()),
+            url: "https://cdn.esoui.com/downloads/file7/LibAddonMenu.zip".to_owned(),
+            sha256: "abc".to_owned(),
+            folders: vec![],
+        };
+        let mut installed = addon("LibAddonMenu-2.0", &[], &[]);
+        let latest = entry("LibAddonMenu-2.0", true);
+        let pinned = AddonEntry {
+            version: Some("2.0 r31".to_owned()),
+            ..entry("LibAddonMenu-2.0", true)
+        };
+
+        let up_to_date = locked("2.0 r32", Some("07/12/22 09:58 AM"));
+        let reuploaded = locked("2.0 r32", Some("01/01/22 10:00 AM"));
+        let old = locked("2.0 r31", None);
+
+        assert!(is_up_to_date(
+            &latest,
+            &remote,
+            Some(&up_to_date),
+            Some(&installed)
+        ));
+        assert!(!is_up_to_date(&latest, &remote, Some(&up_to_date), None));
+        assert!(!is_up_to_date(
+            &latest,
+            &remote,
+            Some(&reuploaded),
+            Some(&installed)
+        ));
+        assert!(!is_up_to_date(
+            &latest,
+            &remote,
+            Some(&old),
+            Some(&installed)
+        ));
+        assert!(is_up_to_date(
+            &pinned,
+            &remote,
+            Some(&old),
+            Some(&installed)
+        ));
+
+        assert!(!is_up_to_date(&latest, &remote, None, Some(&installed)));
+        installed.manifest.version = Some("2.0 r32".to_owned());
+        assert!(is_up_to_date(&latest, &remote, None, Some(&installed)));
+    }
+
+    #[test]
+    fn test_get_pending_changes() {
+        let change = |version: &str| ChangelogEntry {
+            version: version.to_owned(),
+            changes: vec![format!("- released {}", version)],
+        };
+        let remote = RemoteAddon {
+            url: "https://www.esoui.com/downloads/download7".to_owned(),
+            file_id: Some(7),
+            version: Some("2.0 r32".to_owned()),
+            updated: None,
+            download_link: None,
+            md5: None,
+            compatibility: None,
+            description: None,
+            changelog: vec![change("v2.0 r32"), change("2.0 r31"), change("2.0 r30")],
+            archived_files: vec![],
+        };
+        let latest = entry("LibAddonMenu-2.0", true);
+        let pinned = AddonEntry {
+            version: Some("2.0 r31".to_owned()),
+            ..entry("LibAddonMenu-2.0", true)
+        };
+        let versions = |changes: &[ChangelogEntry]| -> Vec<String> {
+            changes.iter().map(|c| c.version.clone()).collect()
+        };
+
+        assert_eq!(
+            versions(get_pending_changes(&latest, &remote, Some("2.0r30"))),
+            vec!["v2.0 r32", "2.0 r31"]
+        );
+        assert!(get_pending_changes(&latest, &remote, Some("2.0 r32")).is_empty());
+        assert_eq!(
+            versions(get_pending_changes(&pinned, &remote, Some("2.0 r30"))),
+            vec!["2.0 r31"]
+        );
+        assert!(get_pending_changes(&pinned, &remote, Some("2.0 r32")).is_empty());
+        assert_eq!(get_pending_changes(&latest, &remote, None).len(), 3);
+        assert_eq!(get_pending_changes(&latest, &remote, Some("1.0")).len(), 3);
+    }
+
+    #[test]
+    fn test_optional_dependencies() {
+        let installed = vec![
+            addon(
+                "SkyShards",
+                &["LibGPS"],
+                &["LibMapPins-1.0", "LibDebugLogger"],
+            ),
+            addon("LibGPS", &[], &[]),
+            addon("LibMapPins-1.0", &[], &[]),
+            addon("LibChatMessage", &[], &[]),
+        ];
+        let desired = vec![
+            entry("SkyShards", false),
+            entry("LibGPS", true),
+            entry("LibMapPins-1.0", true),
+            entry("LibChatMessage", true),
+        ];
+
+        assert_eq!(
+            get_missing_dependencies(&installed).count(),
+            0,
+            "optional dependencies are not required"
+        );
+        assert_eq!(
+            get_missing_optional_dependencies(&installed),
+            vec!["LibDebugLogger".to_owned()]
+        );
+        assert_eq!(
+            get_unused_dependencies(&installed, &desired),
+            vec!["LibChatMessage".to_owned()]
+        );
+    }
+
+    fn search_result(id: u32, name: &str) -> SearchResult {
+        SearchResult {
+            id,
+            name: name.to_owned(),
+            url: format!("https://www.esoui.com/downloads/fileinfo.php?id={}", id),
+            author: None,
+            downloads: None,
+            updated: None,
+            compatibility: None,
         }
     }
 
-    unused_addons
+    #[test]
+    fn test_get_dependency_candidates() {
+        let results = vec![
+            search_result(1, "LibAddonMenu Extension"),
+            search_result(7, "LibAddonMenu"),
+            search_result(563, "LibMapPins"),
+        ];
+
+        let candidates = get_dependency_candidates("LibAddonMenu-2.0", results);
+
+        assert_eq!(
+            candidates,
+            vec![
+                search_result(7, "LibAddonMenu"),
+                search_result(1, "LibAddonMenu Extension"),
+            ]
+        );
+    }
 }
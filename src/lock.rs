@@ -0,0 +1,166 @@
+use super::addons::Download;
+use super::errors::*;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Exact archive installed for an addon from the config file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LockedAddon {
+    pub name: String,
+    #[serde(rename = "fileId", default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    pub url: String,
+    pub sha256: String,
+    #[serde(default)]
+    pub folders: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct LockFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addons: Vec<LockedAddon>,
+}
+
+impl LockedAddon {
+    pub fn new(name: &str, download: &Download) -> LockedAddon {
+        LockedAddon {
+            name: name.to_owned(),
+            file_id: download.file_id,
+            version: download.version.clone(),
+            updated: download.updated.clone(),
+            url: download.download_url.clone(),
+            sha256: download.sha256.clone(),
+            folders: download.folders.clone(),
+        }
+    }
+}
+
+impl LockFile {
+    pub fn get(&self, name: &str) -> Option<&LockedAddon> {
+        self.addons.iter().find(|locked| locked.name == name)
+    }
+
+    pub fn insert(&mut self, locked: LockedAddon) {
+        match self.addons.iter_mut().find(|l| l.name == locked.name) {
+            Some(existing) => *existing = locked,
+            None => self.addons.push(locked),
+        }
+        self.addons.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    /// Folders installed by the locked version of the addon, which are not
+    /// part of the `current` version and not used by any other addon.
+    pub fn get_stale_folders(&self, name: &str, current: &[String]) -> Vec<String> {
+        let locked = match self.get(name) {
+            Some(locked) => locked,
+            None => return vec![],
+        };
+
+        locked
+            .folders
+            .iter()
+            .filter(|folder| !current.contains(folder))
+            .filter(|folder| {
+                !self
+                    .addons
+                    .iter()
+                    .any(|other| other.name != name && other.folders.contains(folder))
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<LockedAddon> {
+        let idx = self.addons.iter().position(|locked| locked.name == name)?;
+        Some(self.addons.remove(idx))
+    }
+}
+
+/// The lock file is kept next to the config file, e.g. `eso-addons.toml`
+/// is locked in `eso-addons.lock`.
+pub fn get_lock_file_path(config_path: &Path) -> PathBuf {
+    config_path.with_extension("lock")
+}
+
+pub fn parse_lock_file(path: &Path) -> Result<LockFile> {
+    if !path.exists() {
+        return Ok(LockFile::default());
+    }
+
+    let data = fs::read_to_string(path).map_err(|err| Error::CannotLoadLockFile(Box::new(err)))?;
+    let lock_file: LockFile =
+        toml::from_str(&data).map_err(|err| Error::CannotLoadLockFile(Box::new(err)))?;
+    Ok(lock_file)
+}
+
+pub fn save_lock_file(path: &Path, lock_file: &LockFile) -> Result<()> {
+    let data = toml::to_string(lock_file).map_err(|err| Error::Other(Box::new(err)))?;
+    fs::write(path, data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(name: &str) -> LockedAddon {
+        LockedAddon {
+            name: name.to_owned(),
+            file_id: Some(7),
+            version: Some("2.0.32".to_owned()),
+            updated: Some("07/12/22 09:58 AM".to_owned()),
+            url: "https://cdn.esoui.com/downloads/file7/LibAddonMenu-2.0r32.zip".to_owned(),
+            sha256: "abc".to_owned(),
+            folders: vec![name.to_owned()],
+        }
+    }
+
+    #[test]
+    fn test_lock_file_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = get_lock_file_path(&dir.path().join("eso-addons.toml"));
+        assert_eq!(path, dir.path().join("eso-addons.lock"));
+
+        assert_eq!(parse_lock_file(&path).unwrap(), LockFile::default());
+
+        let mut lock_file = LockFile::default();
+        lock_file.insert(locked("LibDebugLogger"));
+        lock_file.insert(locked("LibAddonMenu-2.0"));
+        lock_file.insert(LockedAddon {
+            sha256: "def".to_owned(),
+            ..locked("LibDebugLogger")
+        });
+
+        save_lock_file(&path, &lock_file).unwrap();
+        let parsed = parse_lock_file(&path).unwrap();
+
+        assert_eq!(parsed, lock_file);
+        assert_eq!(parsed.addons[0].name, "LibAddonMenu-2.0");
+        assert_eq!(parsed.get("LibDebugLogger").unwrap().sha256, "def");
+    }
+
+    #[test]
+    fn test_get_stale_folders() {
+        let mut lock_file = LockFile::default();
+        lock_file.insert(LockedAddon {
+            folders: vec![
+                "CombatMetrics".to_owned(),
+                "CombatMetricsFightData".to_owned(),
+                "LibCombat".to_owned(),
+            ],
+            ..locked("CombatMetrics")
+        });
+        lock_file.insert(locked("LibCombat"));
+
+        assert_eq!(
+            lock_file.get_stale_folders("CombatMetrics", &["CombatMetrics".to_owned()]),
+            vec!["CombatMetricsFightData".to_owned()]
+        );
+        assert!(lock_file.get_stale_folders("Unknown", &[]).is_empty());
+    }
+}
@@ -7,8 +7,8 @@ extern crate prettytable;
 mod cli;
 
 fn main() {
-    match cli::run() {
-        Err(err) => println!("{}", err),
-        Ok(()) => {}
+    if let Err(err) = cli::run() {
+        println!("{}", err);
+        std::process::exit(err.exit_code());
     }
 }
@@ -0,0 +1,385 @@
+use regex::Regex;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const BOM: char = '\u{feff}';
+
+/// Manifest extensions in the order the game prefers them.
+pub const MANIFEST_EXTENSIONS: [&str; 2] = ["addon", "txt"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    pub title: Option<String>,
+    pub version: Option<String>,
+    pub addon_version: Option<u32>,
+    pub api_version: Vec<u32>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub saved_variables: Vec<String>,
+    pub saved_variables_per_character: Vec<String>,
+    pub depends_on: Vec<Dependency>,
+    pub optional_depends_on: Vec<Dependency>,
+    pub is_library: bool,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionOperator {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+/// Requirement on the `## AddOnVersion` of a dependency, e.g. `>=32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionConstraint {
+    pub operator: VersionOperator,
+    pub version: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    pub constraint: Option<VersionConstraint>,
+}
+
+impl VersionConstraint {
+    pub fn matches(&self, version: u32) -> bool {
+        match self.operator {
+            VersionOperator::Less => version < self.version,
+            VersionOperator::LessOrEqual => version <= self.version,
+            VersionOperator::Equal => version == self.version,
+            VersionOperator::GreaterOrEqual => version >= self.version,
+            VersionOperator::Greater => version > self.version,
+        }
+    }
+}
+
+impl Dependency {
+    /// Checks the constraint against the installed `## AddOnVersion`.
+    /// Like the game, a missing AddOnVersion is treated as 0.
+    pub fn is_satisfied_by(&self, addon_version: Option<u32>) -> bool {
+        match self.constraint {
+            Some(constraint) => constraint.matches(addon_version.unwrap_or(0)),
+            None => true,
+        }
+    }
+}
+
+impl std::fmt::Display for VersionOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            VersionOperator::Less => "<",
+            VersionOperator::LessOrEqual => "<=",
+            VersionOperator::Equal => "=",
+            VersionOperator::GreaterOrEqual => ">=",
+            VersionOperator::Greater => ">",
+        })
+    }
+}
+
+impl std::fmt::Display for VersionConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.operator, self.version)
+    }
+}
+
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.constraint {
+            Some(constraint) => write!(f, "{}{}", self.name, constraint),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+impl Manifest {
+    pub fn parse<R: Read>(mut reader: R) -> std::io::Result<Manifest> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+
+        // manifests are not always valid UTF-8, so don't fail on them
+        Ok(Self::parse_str(&String::from_utf8_lossy(&buf)))
+    }
+
+    pub fn parse_str(data: &str) -> Manifest {
+        let directive_re = Regex::new(r"^##\s*([A-Za-z]+)\s*:(.*)$").unwrap();
+
+        let mut manifest = Manifest::default();
+
+        for line in data.trim_start_matches(BOM).lines() {
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with("##") {
+                if let Some(captures) = directive_re.captures(line) {
+                    manifest.apply_directive(&captures[1], captures[2].trim());
+                }
+                continue;
+            }
+
+            if line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            manifest.files.push(line.replace('\\', "/"));
+        }
+
+        manifest
+    }
+
+    fn apply_directive(&mut self, key: &str, value: &str) {
+        match key {
+            "Title" => self.title = Some(strip_color_codes(value)),
+            "Version" => self.version = Some(value.to_owned()),
+            "AddOnVersion" => self.addon_version = value.parse().ok(),
+            "APIVersion" => self.api_version.extend(
+                value
+                    .split_whitespace()
+                    .filter_map(|v| v.parse::<u32>().ok()),
+            ),
+            "Author" => self.author = Some(strip_color_codes(value)),
+            "Description" => {
+                self.description = match self.description.take() {
+                    Some(description) => Some(format!("{} {}", description, value)),
+                    None => Some(value.to_owned()),
+                }
+            }
+            "SavedVariables" => self.saved_variables.extend(split_list(value)),
+            "SavedVariablesPerCharacter" => {
+                self.saved_variables_per_character.extend(split_list(value))
+            }
+            "DependsOn" => self
+                .depends_on
+                .extend(split_list(value).filter_map(|dep| extract_dependency(&dep))),
+            "OptionalDependsOn" => self
+                .optional_depends_on
+                .extend(split_list(value).filter_map(|dep| extract_dependency(&dep))),
+            "IsLibrary" => self.is_library = value.eq_ignore_ascii_case("true"),
+            _ => {}
+        }
+    }
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value.split_whitespace().map(|s| s.to_owned())
+}
+
+fn extract_dependency(dep: &str) -> Option<Dependency> {
+    let re = Regex::new(r"^(.+?)(([<=>]+)(.*))?$").unwrap();
+    let captures = re.captures(dep)?;
+
+    let constraint = match (captures.get(3), captures.get(4)) {
+        (Some(operator), Some(version)) => {
+            let operator = match operator.as_str() {
+                "<" => VersionOperator::Less,
+                "<=" => VersionOperator::LessOrEqual,
+                "=" | "==" => VersionOperator::Equal,
+                ">=" => VersionOperator::GreaterOrEqual,
+                ">" => VersionOperator::Greater,
+                _ => return None,
+            };
+            let version = version.as_str().parse().ok()?;
+            Some(VersionConstraint { operator, version })
+        }
+        _ => None,
+    };
+
+    Some(Dependency {
+        name: captures[1].to_owned(),
+        constraint,
+    })
+}
+
+/// Finds the manifest the game would load for the addon in `dir`.
+///
+/// The manifest has to be named after the folder. `.addon` manifests take
+/// precedence over `.txt` ones and, like on Windows, the name is matched
+/// case-insensitively.
+pub fn find_manifest_file(dir: &Path) -> Option<PathBuf> {
+    let addon_name = dir.file_name()?.to_str()?;
+
+    let files: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .map(|entry| entry.path())
+        .collect();
+
+    for extension in MANIFEST_EXTENSIONS.iter() {
+        let expected = format!("{}.{}", addon_name, extension);
+
+        let exact = files
+            .iter()
+            .find(|path| path.file_name().and_then(|n| n.to_str()) == Some(&expected));
+        let found = exact.or_else(|| {
+            files.iter().find(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.eq_ignore_ascii_case(&expected))
+                    .unwrap_or(false)
+            })
+        });
+
+        if let Some(path) = found {
+            return Some(path.to_owned());
+        }
+    }
+
+    None
+}
+
+/// Removes the `|cRRGGBB`, `|r` and `|t...|t` markup used by the game to color text.
+pub fn strip_color_codes(text: &str) -> String {
+    let re = Regex::new(r"\|c[0-9A-Fa-f]{6}|\|r|\|t[^|]*\|t").unwrap();
+    re.replace_all(text, "").trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let data = "\u{feff}## Title: |c00FF00Action|r Duration Reminder\r
+## Author: |cFFFFFFPhinix|r\r
+## APIVersion: 101033 101034\r
+## Version: 1.2.3\r
+## AddOnVersion: 10203\r
+## IsLibrary: false\r
+## Description: Reminds you\r
+## Description: about your buffs\r
+## SavedVariables: ADR_SV ADR_Global\r
+## SavedVariablesPerCharacter: ADR_Char\r
+## DependsOn: LibAddonMenu-2.0>=32 LibDebugLogger\r
+## DependsOn: LibChatMessage\r
+## OptionalDependsOn: LibMapPins-1.0\r
+\r
+# a comment\r
+; another comment\r
+lang\\en.lua\r
+ActionDurationReminder.lua\r
+ActionDurationReminder.xml\r
+";
+
+        let manifest = Manifest::parse_str(data);
+
+        assert_eq!(
+            manifest,
+            Manifest {
+                title: Some("Action Duration Reminder".to_owned()),
+                version: Some("1.2.3".to_owned()),
+                addon_version: Some(10203),
+                api_version: vec![101033, 101034],
+                author: Some("Phinix".to_owned()),
+                description: Some("Reminds you about your buffs".to_owned()),
+                saved_variables: vec!["ADR_SV".to_owned(), "ADR_Global".to_owned()],
+                saved_variables_per_character: vec!["ADR_Char".to_owned()],
+                depends_on: vec![
+                    Dependency {
+                        name: "LibAddonMenu-2.0".to_owned(),
+                        constraint: Some(VersionConstraint {
+                            operator: VersionOperator::GreaterOrEqual,
+                            version: 32
+                        }),
+                    },
+                    Dependency {
+                        name: "LibDebugLogger".to_owned(),
+                        constraint: None,
+                    },
+                    Dependency {
+                        name: "LibChatMessage".to_owned(),
+                        constraint: None,
+                    },
+                ],
+                optional_depends_on: vec![Dependency {
+                    name: "LibMapPins-1.0".to_owned(),
+                    constraint: None,
+                }],
+                is_library: false,
+                files: vec![
+                    "lang/en.lua".to_owned(),
+                    "ActionDurationReminder.lua".to_owned(),
+                    "ActionDurationReminder.xml".to_owned()
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_dependency_constraints() {
+        let tests: Vec<(&str, Option<u32>, bool)> = vec![
+            ("LibAddonMenu-2.0", None, true),
+            ("LibAddonMenu-2.0>=32", Some(32), true),
+            ("LibAddonMenu-2.0>=32", Some(31), false),
+            ("LibAddonMenu-2.0>=32", None, false),
+            ("LibAddonMenu-2.0>32", Some(32), false),
+            ("LibAddonMenu-2.0=32", Some(32), true),
+            ("LibAddonMenu-2.0<32", Some(31), true),
+            ("LibAddonMenu-2.0<=32", Some(33), false),
+        ];
+
+        for test in tests {
+            let dependency = extract_dependency(test.0).unwrap();
+            assert_eq!(dependency.name, "LibAddonMenu-2.0");
+            assert_eq!(dependency.to_string(), test.0);
+            assert!(
+                dependency.is_satisfied_by(test.1) == test.2,
+                "{} with {:?}",
+                test.0,
+                test.1
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_manifest_file() {
+        let root = tempfile::tempdir().unwrap();
+
+        let txt_only = root.path().join("TxtOnly");
+        fs::create_dir(&txt_only).unwrap();
+        fs::write(txt_only.join("TxtOnly.txt"), "").unwrap();
+
+        let both = root.path().join("Both");
+        fs::create_dir(&both).unwrap();
+        fs::write(both.join("Both.txt"), "").unwrap();
+        fs::write(both.join("Both.addon"), "").unwrap();
+
+        let lowercase = root.path().join("Lowercase");
+        fs::create_dir(&lowercase).unwrap();
+        fs::write(lowercase.join("lowercase.addon"), "").unwrap();
+
+        let missing = root.path().join("Missing");
+        fs::create_dir(&missing).unwrap();
+        fs::write(missing.join("Other.txt"), "").unwrap();
+
+        assert_eq!(
+            find_manifest_file(&txt_only),
+            Some(txt_only.join("TxtOnly.txt"))
+        );
+        assert_eq!(find_manifest_file(&both), Some(both.join("Both.addon")));
+        assert_eq!(
+            find_manifest_file(&lowercase),
+            Some(lowercase.join("lowercase.addon"))
+        );
+        assert_eq!(find_manifest_file(&missing), None);
+    }
+
+    #[test]
+    fn test_parse_library_manifest() {
+        let data = "##Title:LibDebugLogger\n##IsLibrary:true\n##AddOnVersion:x\nLibDebugLogger.lua";
+
+        let manifest = Manifest::parse_str(data);
+
+        assert_eq!(manifest.title, Some("LibDebugLogger".to_owned()));
+        assert!(manifest.is_library);
+        assert_eq!(manifest.addon_version, None);
+        assert_eq!(manifest.files, vec!["LibDebugLogger.lua".to_owned()]);
+    }
+}
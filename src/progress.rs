@@ -0,0 +1,110 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Progress of a single download, reported to the callback passed to the
+/// `fetch_*` methods of [`crate::addons::Manager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    /// The download started, `total` is the size announced by the server.
+    Started { total: Option<u64> },
+    /// `downloaded` bytes of the archive were received so far.
+    Progress { downloaded: u64, total: Option<u64> },
+    /// The whole archive was received.
+    Finished { downloaded: u64, elapsed: Duration },
+}
+
+impl Transfer {
+    /// Average transfer rate in bytes per second of a finished download.
+    pub fn rate(&self) -> Option<f64> {
+        match self {
+            Transfer::Finished {
+                downloaded,
+                elapsed,
+            } if !elapsed.is_zero() => Some(*downloaded as f64 / elapsed.as_secs_f64()),
+            _ => None,
+        }
+    }
+}
+
+/// Reports the bytes written through it as [`Transfer`] events.
+pub(crate) struct ProgressWriter<'a, W: Write> {
+    inner: W,
+    downloaded: u64,
+    total: Option<u64>,
+    started: Instant,
+    on_transfer: &'a dyn Fn(Transfer),
+}
+
+impl<'a, W: Write> ProgressWriter<'a, W> {
+    pub(crate) fn new(
+        inner: W,
+        total: Option<u64>,
+        on_transfer: &'a dyn Fn(Transfer),
+    ) -> ProgressWriter<'a, W> {
+        on_transfer(Transfer::Started { total });
+
+        ProgressWriter {
+            inner,
+            downloaded: 0,
+            total,
+            started: Instant::now(),
+            on_transfer,
+        }
+    }
+
+    pub(crate) fn finish(self) -> W {
+        (self.on_transfer)(Transfer::Finished {
+            downloaded: self.downloaded,
+            elapsed: self.started.elapsed(),
+        });
+        self.inner
+    }
+}
+
+impl<'a, W: Write> Write for ProgressWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.downloaded += written as u64;
+        (self.on_transfer)(Transfer::Progress {
+            downloaded: self.downloaded,
+            total: self.total,
+        });
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_progress_writer() {
+        let events = RefCell::new(vec![]);
+        let on_transfer = |transfer| events.borrow_mut().push(transfer);
+
+        let mut writer = ProgressWriter::new(vec![], Some(6), &on_transfer);
+        writer.write_all(b"abc").unwrap();
+        writer.write_all(b"def").unwrap();
+        assert_eq!(writer.finish(), b"abcdef");
+
+        let events = events.into_inner();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0], Transfer::Started { total: Some(6) });
+        assert_eq!(
+            events[2],
+            Transfer::Progress {
+                downloaded: 6,
+                total: Some(6)
+            }
+        );
+        assert!(matches!(
+            events[3],
+            Transfer::Finished { downloaded: 6, .. }
+        ));
+    }
+}
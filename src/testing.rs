@@ -0,0 +1,31 @@
+//! Fixtures shared by the unit tests.
+
+use std::path::PathBuf;
+
+use crate::addons::Addon;
+use crate::manifest::{Dependency, Manifest};
+
+/// An installed addon at `path`, named after its folder, with unversioned
+/// dependencies.
+pub fn addon(path: &str, depends_on: &[&str], optional_depends_on: &[&str]) -> Addon {
+    let path = PathBuf::from(path);
+    let to_dependencies = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| Dependency {
+                name: name.to_string(),
+                constraint: None,
+            })
+            .collect()
+    };
+
+    Addon {
+        name: path.file_name().unwrap().to_str().unwrap().to_owned(),
+        path,
+        manifest: Manifest {
+            depends_on: to_dependencies(depends_on),
+            optional_depends_on: to_dependencies(optional_depends_on),
+            ..Default::default()
+        },
+    }
+}
@@ -0,0 +1,360 @@
+extern crate tempfile;
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use common::EsouiServer;
+use eso_addons::config::{self, Config};
+use eso_addons::lock;
+
+const LIB_DEBUG_LOGGER: u32 = 2275;
+const COMBAT_METRICS: u32 = 1360;
+
+/// Runs the binary against a local esoui.com with its own home directory,
+/// so that neither the user's config nor their download cache are used.
+struct Cli {
+    server: EsouiServer,
+    home: tempfile::TempDir,
+}
+
+impl Cli {
+    fn new() -> Cli {
+        let server = EsouiServer::start();
+        server.publish(
+            LIB_DEBUG_LOGGER,
+            "LibDebugLogger",
+            "2.4.1",
+            "LibDebugLogger-2.4",
+        );
+        server.publish(
+            COMBAT_METRICS,
+            "Combat Metrics",
+            "1.0.0",
+            "CombatMetrics-1.0",
+        );
+
+        let cli = Cli {
+            server,
+            home: tempfile::tempdir().unwrap(),
+        };
+        fs::create_dir_all(cli.addon_dir()).unwrap();
+        config::save_config(
+            &cli.config_path(),
+            &Config {
+                addon_dir: cli.addon_dir(),
+                http: cli.server.http_settings(),
+                extract: Default::default(),
+                addons: vec![],
+            },
+        )
+        .unwrap();
+
+        cli
+    }
+
+    fn addon_dir(&self) -> PathBuf {
+        self.home.path().join("AddOns")
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.home.path().join("eso-addons.toml")
+    }
+
+    fn config(&self) -> Config {
+        config::parse_config(&self.config_path()).unwrap()
+    }
+
+    /// Runs the binary without checking the exit status.
+    fn output(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_eso-addons"))
+            .arg("--config")
+            .arg(self.config_path())
+            .args(args)
+            .env("HOME", self.home.path())
+            .env("XDG_CACHE_HOME", self.home.path().join("cache"))
+            .env("NO_PROXY", "127.0.0.1")
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap()
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        let output = self.output(args);
+        assert!(
+            output.status.success(),
+            "eso-addons {:?} failed with {}:\n{}{}",
+            args,
+            output.status,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+
+    fn installed(&self, path: &str) -> bool {
+        self.addon_dir().join(path).exists()
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn folders(config: &Config, name: &str) -> Vec<String> {
+    let entry = config.addons.iter().find(|entry| entry.name == name);
+    entry.unwrap().folders.clone()
+}
+
+fn write_addon(addon_dir: &Path, name: &str) {
+    fs::create_dir_all(addon_dir.join(name)).unwrap();
+    fs::write(
+        addon_dir.join(name).join(format!("{}.txt", name)),
+        format!("## Title: {}\n## Version: 1.0\n", name),
+    )
+    .unwrap();
+}
+
+#[test]
+fn add_installs_addon_with_dependencies() {
+    let cli = Cli::new();
+
+    let output = cli.run(&["add", &cli.server.info_url(COMBAT_METRICS), "--yes"]);
+    assert!(stdout(&output).contains("Installed CombatMetrics"));
+
+    assert!(cli.installed("CombatMetrics/lang/en.lua"));
+    assert!(cli.installed("LibCombat/LibCombat.txt"));
+    assert!(cli.installed("LibDebugLogger/LibDebugLogger.txt"));
+
+    let config = cli.config();
+    assert_eq!(
+        folders(&config, "CombatMetrics"),
+        vec!["CombatMetrics", "LibCombat"]
+    );
+    assert_eq!(
+        config.addons[0].url,
+        Some(cli.server.download_url(COMBAT_METRICS))
+    );
+    let dependency = config.addons.iter().find(|e| e.name == "LibDebugLogger");
+    assert!(dependency.unwrap().dependency);
+
+    let lock_file = lock::parse_lock_file(&lock::get_lock_file_path(&cli.config_path())).unwrap();
+    assert_eq!(lock_file.addons.len(), 2);
+}
+
+#[test]
+fn add_continues_when_dependency_search_fails() {
+    let cli = Cli::new();
+    cli.server.break_search();
+
+    let output = stdout(&cli.run(&["add", &cli.server.info_url(COMBAT_METRICS), "--yes"]));
+    assert!(output.contains("Failed LibDebugLogger"), "{}", output);
+
+    assert!(cli.installed("CombatMetrics"));
+    assert_eq!(cli.config().addons.len(), 1);
+    let lock_file = lock::parse_lock_file(&lock::get_lock_file_path(&cli.config_path())).unwrap();
+    assert_eq!(lock_file.addons.len(), 1);
+}
+
+#[test]
+fn add_offline_fails_without_request() {
+    let cli = Cli::new();
+
+    let output = cli.output(&["add", &cli.server.info_url(LIB_DEBUG_LOGGER), "--offline"]);
+    assert!(!output.status.success());
+    assert!(cli.server.requests().is_empty());
+    assert!(cli.config().addons.is_empty());
+}
+
+#[test]
+fn update_installs_new_releases() {
+    let cli = Cli::new();
+    cli.run(&["add", &cli.server.info_url(LIB_DEBUG_LOGGER)]);
+    assert!(cli.installed("LibDebugLogger/Compatibility.lua"));
+
+    cli.server.publish(
+        LIB_DEBUG_LOGGER,
+        "LibDebugLogger",
+        "2.5.0",
+        "LibDebugLogger-2.5",
+    );
+    let output = cli.run(&["update", "--yes"]);
+    assert!(stdout(&output).contains("2.5.0"), "{}", stdout(&output));
+
+    assert!(cli.installed("LibDebugLogger/lang/en.lua"));
+    assert!(!cli.installed("LibDebugLogger/Compatibility.lua"));
+
+    let lock_file = lock::parse_lock_file(&lock::get_lock_file_path(&cli.config_path())).unwrap();
+    assert_eq!(lock_file.addons[0].version.as_deref(), Some("2.5.0"));
+
+    // nothing is downloaded, when all addons are up to date
+    let downloads = |server: &EsouiServer| {
+        server
+            .requests()
+            .iter()
+            .filter(|url| url.starts_with("/cdn/"))
+            .count()
+    };
+    let before = downloads(&cli.server);
+    cli.run(&["update", "--yes"]);
+    assert_eq!(downloads(&cli.server), before);
+}
+
+#[test]
+fn outdated_exit_status_tells_updates_from_failures() {
+    let cli = Cli::new();
+    cli.run(&["add", &cli.server.info_url(LIB_DEBUG_LOGGER)]);
+
+    let output = cli.run(&["outdated"]);
+    assert!(stdout(&output).contains("All addons are up to date"));
+
+    cli.server.publish(
+        LIB_DEBUG_LOGGER,
+        "LibDebugLogger",
+        "2.5.0",
+        "LibDebugLogger-2.5",
+    );
+    let output = cli.output(&["outdated"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).contains("2.5.0"));
+
+    let output = cli.output(&["outdated", "--offline"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!stdout(&output).contains("up to date"));
+}
+
+#[test]
+fn search_lists_matching_addons() {
+    let cli = Cli::new();
+
+    let output = stdout(&cli.run(&["search", "lib", "debug", "--list"]));
+    assert!(output.contains("LibDebugLogger"), "{}", output);
+    assert!(output.contains(common::AUTHOR));
+    assert!(output.contains("1,024"));
+    assert!(output.contains("10/01/26 09:58 AM"));
+    assert!(output.contains("Gold Road (10.0.0)"));
+    assert!(!output.contains("Combat Metrics"));
+
+    let output = stdout(&cli.run(&["search", "LibUnknown", "--list"]));
+    assert!(output.contains("No addons found for LibUnknown"));
+}
+
+#[test]
+fn info_shows_local_and_remote_details() {
+    let cli = Cli::new();
+    cli.run(&["add", &cli.server.info_url(LIB_DEBUG_LOGGER)]);
+    cli.server.publish(
+        LIB_DEBUG_LOGGER,
+        "LibDebugLogger",
+        "2.5.0",
+        "LibDebugLogger-2.5",
+    );
+
+    let output = stdout(&cli.run(&["info", "LibDebugLogger"]));
+    for expected in [
+        // manifest
+        "2.4.1 (241)",
+        "sirinsidiator",
+        "101041, 101042",
+        "LibDebugLoggerSettings, LibDebugLoggerLog",
+        "in 3 files",
+        // esoui.com
+        "Latest version  2.5.0",
+        "Gold Road (10.0.0), Scions of Ithelia (9.3.0)",
+        "Published as version 2.5.0.",
+        // config and lock file
+        &cli.server.download_url(LIB_DEBUG_LOGGER),
+        "Version         2.4.1",
+    ] {
+        assert!(
+            output.contains(expected),
+            "{} missing in:\n{}",
+            expected,
+            output
+        );
+    }
+
+    let output = cli.output(&["info", "LibUnknown"]);
+    assert!(!output.status.success());
+    assert!(stdout(&output).contains("addon LibUnknown not found"));
+}
+
+#[test]
+fn changelog_shows_changes_since_installed_version() {
+    let cli = Cli::new();
+    cli.run(&["add", &cli.server.info_url(LIB_DEBUG_LOGGER)]);
+
+    let output = stdout(&cli.run(&["changelog", "LibDebugLogger"]));
+    assert!(
+        output.contains("No changes since LibDebugLogger 2.4.1"),
+        "{}",
+        output
+    );
+
+    cli.server.publish(
+        LIB_DEBUG_LOGGER,
+        "LibDebugLogger",
+        "2.5.0",
+        "LibDebugLogger-2.5",
+    );
+
+    let output = stdout(&cli.run(&["changelog", "LibDebugLogger"]));
+    assert!(output.contains("2.5.0"), "{}", output);
+    assert!(output.contains("- packed from the LibDebugLogger-2.5 fixture"));
+    assert!(!output.contains("LibDebugLogger-2.4 fixture"));
+
+    let output = stdout(&cli.run(&["changelog", "LibDebugLogger", "--all"]));
+    assert!(output.contains("- packed from the LibDebugLogger-2.4 fixture"));
+
+    let output = stdout(&cli.run(&["update", "--show-changelog"]));
+    assert!(
+        output.contains("Updated LibDebugLogger (2.5.0)!"),
+        "{}",
+        output
+    );
+    assert!(output.contains("- packed from the LibDebugLogger-2.5 fixture"));
+    assert!(!output.contains("LibDebugLogger-2.4 fixture"));
+}
+
+#[test]
+fn why_treats_bundled_folders_as_managed() {
+    let cli = Cli::new();
+    cli.run(&["add", &cli.server.info_url(COMBAT_METRICS), "--yes"]);
+
+    let output = stdout(&cli.run(&["why", "LibDebugLogger"]));
+    assert!(output.contains("- LibCombat"), "{}", output);
+    assert!(!output.contains("not managed"), "{}", output);
+}
+
+#[test]
+fn clean_removes_unmanaged_addons() {
+    let cli = Cli::new();
+    cli.run(&["add", &cli.server.info_url(COMBAT_METRICS), "--no-deps"]);
+    write_addon(&cli.addon_dir(), "Unmanaged");
+
+    let output = cli.run(&["clean", "--remove", "true"]);
+    assert!(stdout(&output).contains("Unmanaged removed"));
+
+    assert!(!cli.installed("Unmanaged"));
+    assert!(cli.installed("CombatMetrics"));
+    assert!(cli.installed("LibCombat"));
+}
+
+#[test]
+fn remove_uninstalls_all_folders_of_addon() {
+    let cli = Cli::new();
+    cli.run(&["add", &cli.server.info_url(COMBAT_METRICS), "--yes"]);
+
+    let output = cli.run(&["remove", "CombatMetrics"]);
+    assert!(stdout(&output).contains("Uninstalled CombatMetrics"));
+
+    assert!(!cli.installed("CombatMetrics"));
+    assert!(!cli.installed("LibCombat"));
+    assert!(cli.installed("LibDebugLogger"));
+
+    let config = cli.config();
+    assert_eq!(config.addons.len(), 1);
+    assert_eq!(config.addons[0].name, "LibDebugLogger");
+}
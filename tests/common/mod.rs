@@ -0,0 +1,360 @@
+//! Serves recorded esoui.com pages and archives packed from the fixture
+//! addons on a local HTTP server, so that the tests run without network.
+
+// every test binary uses a different part of the harness
+#![allow(dead_code)]
+
+extern crate tiny_http;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use eso_addons::http::{HttpClient, HttpSettings};
+use md5::{Digest, Md5};
+use tiny_http::{Header, Response, Server};
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{DateTime, ZipWriter};
+
+/// Author shown for all addons on the pages.
+pub const AUTHOR: &str = "sirinsidiator";
+
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+struct Release {
+    version: String,
+    updated: String,
+    fixture: String,
+    archive_name: String,
+    archive: Vec<u8>,
+    md5: String,
+}
+
+struct Addon {
+    title: String,
+    releases: Vec<Release>,
+}
+
+impl Addon {
+    fn slug(&self) -> String {
+        self.title.replace(' ', "")
+    }
+}
+
+#[derive(Default)]
+struct State {
+    addons: HashMap<u32, Addon>,
+    requests: Vec<String>,
+    search_unavailable: bool,
+}
+
+/// Local stand-in for esoui.com. The CDN is served below `cdn/`.
+pub struct EsouiServer {
+    server: Arc<Server>,
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl EsouiServer {
+    pub fn start() -> EsouiServer {
+        let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+        let url = format!("http://{}/", server.server_addr().to_ip().unwrap());
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let handler = EsouiServer {
+            server: server.clone(),
+            url: url.clone(),
+            state: state.clone(),
+        };
+        thread::spawn(move || {
+            for request in handler.server.incoming_requests() {
+                let response = handler.respond(request.url());
+                let _ = request.respond(response);
+            }
+        });
+
+        EsouiServer { server, url, state }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn cdn_url(&self) -> String {
+        format!("{}cdn/", self.url)
+    }
+
+    pub fn info_url(&self, id: u32) -> String {
+        let state = self.state.lock().unwrap();
+        format!(
+            "{}downloads/info{}-{}.html",
+            self.url,
+            id,
+            state.addons[&id].slug()
+        )
+    }
+
+    pub fn download_url(&self, id: u32) -> String {
+        format!("{}downloads/download{}", self.url, id)
+    }
+
+    /// Settings pointing the HTTP client at this server.
+    pub fn http_settings(&self) -> HttpSettings {
+        HttpSettings {
+            base_url: self.url.clone(),
+            cdn_url: self.cdn_url(),
+            retries: 0,
+            request_interval: 0,
+            ..Default::default()
+        }
+    }
+
+    pub fn client(&self) -> HttpClient {
+        HttpClient::new(&self.http_settings()).unwrap()
+    }
+
+    /// Publishes a new release of the addon `id`, packed from the fixture
+    /// directory `tests/fixtures/addons/<fixture>`. Earlier releases are
+    /// moved to the file history.
+    pub fn publish(&self, id: u32, title: &str, version: &str, fixture: &str) {
+        let archive = pack_fixture(&fixtures_dir().join("addons").join(fixture));
+        let mut state = self.state.lock().unwrap();
+        let addon = state.addons.entry(id).or_insert_with(|| Addon {
+            title: title.to_owned(),
+            releases: vec![],
+        });
+
+        let day = addon.releases.len() + 1;
+        addon.releases.push(Release {
+            version: version.to_owned(),
+            updated: format!("10/{:02}/26 09:58 AM", day),
+            fixture: fixture.to_owned(),
+            archive_name: format!("{}-{}.zip", addon.slug(), version.replace(' ', "")),
+            md5: hex::encode(Md5::digest(&archive)),
+            archive,
+        });
+    }
+
+    /// Replaces the MD5 shown for the latest release of the addon `id`.
+    pub fn publish_md5(&self, id: u32, md5: &str) {
+        let mut state = self.state.lock().unwrap();
+        let release = state.addons.get_mut(&id).unwrap().releases.last_mut();
+        release.unwrap().md5 = md5.to_owned();
+    }
+
+    /// Answers all searches with a server error.
+    pub fn break_search(&self) {
+        self.state.lock().unwrap().search_unavailable = true;
+    }
+
+    /// Paths and queries of all requests received so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    fn respond(&self, url: &str) -> Response<Cursor<Vec<u8>>> {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(url.to_owned());
+
+        let (path, query) = match url.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (url, ""),
+        };
+        let param = |name: &str| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.replace('+', " "))
+        };
+        let id_in = |rest: &str| -> Option<u32> {
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        };
+
+        if path == "/downloads/search.php" && state.search_unavailable {
+            return Response::from_string("Internal Server Error").with_status_code(500);
+        }
+
+        let page = if let Some(rest) = path.strip_prefix("/downloads/info") {
+            id_in(rest).and_then(|id| self.addon_page(&state, id))
+        } else if let Some(rest) = path.strip_prefix("/downloads/download") {
+            id_in(rest).and_then(|id| self.addon_page(&state, id))
+        } else if path == "/downloads/fileinfo.php" {
+            param("id")
+                .and_then(|id| id.parse().ok())
+                .and_then(|id| self.addon_page(&state, id))
+        } else if path == "/downloads/search.php" {
+            param("search").map(|query| self.search_page(&state, &query))
+        } else {
+            None
+        };
+        if let Some(page) = page {
+            let header = Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap();
+            return Response::from_string(page).with_header(header);
+        }
+
+        let archive = if let Some(rest) = path.strip_prefix("/cdn/downloads/file") {
+            id_in(rest)
+                .and_then(|id| state.addons.get(&id))
+                .and_then(|addon| {
+                    addon
+                        .releases
+                        .iter()
+                        .find(|release| path.ends_with(&format!("/{}", release.archive_name)))
+                })
+        } else if path == "/downloads/getfile.php" {
+            let id = param("id").and_then(|id| id.parse::<u32>().ok());
+            let aid = param("aid").and_then(|aid| aid.parse::<usize>().ok());
+            match (id.and_then(|id| state.addons.get(&id)), aid) {
+                (Some(addon), Some(aid)) => addon.releases.get(aid),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        match archive {
+            Some(release) => Response::from_data(release.archive.clone()),
+            None => Response::from_string("Not Found").with_status_code(404),
+        }
+    }
+
+    fn addon_page(&self, state: &State, id: u32) -> Option<String> {
+        let addon = state.addons.get(&id)?;
+        let latest = addon.releases.last()?;
+
+        let archived: String = addon
+            .releases
+            .iter()
+            .enumerate()
+            .rev()
+            .skip(1)
+            .map(|(aid, release)| {
+                render(
+                    "archived.html",
+                    &[
+                        ("id", &id.to_string()),
+                        ("aid", &aid.to_string()),
+                        ("version", &release.version),
+                        ("updated", &release.updated),
+                        ("archive", &release.archive_name),
+                        ("size", &format!("{} B", release.archive.len())),
+                    ],
+                )
+            })
+            .collect();
+
+        // one entry per release, newest first
+        let changelog: String = addon
+            .releases
+            .iter()
+            .rev()
+            .map(|release| {
+                format!(
+                    "<b>v{}</b><br />\n- packed from the {} fixture<br />\n<br />\n",
+                    release.version, release.fixture
+                )
+            })
+            .collect();
+
+        Some(render(
+            "addon.html",
+            &[
+                ("base", &self.url),
+                ("cdn", &self.cdn_url()),
+                ("id", &id.to_string()),
+                ("title", &addon.title),
+                ("slug", &addon.slug()),
+                ("version", &latest.version),
+                ("updated", &latest.updated),
+                ("archive", &latest.archive_name),
+                ("timestamp", &addon.releases.len().to_string()),
+                ("md5", &latest.md5),
+                ("archived", &archived),
+                ("changelog", &changelog),
+            ],
+        ))
+    }
+
+    fn search_page(&self, state: &State, query: &str) -> String {
+        let normalize = |name: &str| -> String {
+            name.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        };
+
+        let mut ids: Vec<&u32> = state.addons.keys().collect();
+        ids.sort();
+        let results: String = ids
+            .into_iter()
+            .filter(|id| normalize(&state.addons[id].title).contains(&normalize(query)))
+            .map(|id| {
+                let addon = &state.addons[id];
+                let updated = addon
+                    .releases
+                    .last()
+                    .map(|release| release.updated.as_str());
+                render(
+                    "result.html",
+                    &[
+                        ("id", &id.to_string()),
+                        ("title", &addon.title),
+                        ("slug", &addon.slug()),
+                        ("author", AUTHOR),
+                        ("updated", updated.unwrap_or_default()),
+                    ],
+                )
+            })
+            .collect();
+
+        render("search.html", &[("base", &self.url), ("results", &results)])
+    }
+}
+
+impl Drop for EsouiServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+/// Renders a page from `tests/fixtures/pages`, replacing `{{key}}`.
+fn render(page: &str, values: &[(&str, &str)]) -> String {
+    let mut html = fs::read_to_string(fixtures_dir().join("pages").join(page)).unwrap();
+    for (key, value) in values {
+        html = html.replace(&format!("{{{{{}}}}}", key), value);
+    }
+    html
+}
+
+/// Packs the files below `dir` into a zip archive. The entries have a fixed
+/// date, so the same fixture always gives the same archive.
+fn pack_fixture(dir: &Path) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(vec![]));
+    let options = FileOptions::default().last_modified_time(DateTime::default());
+
+    for entry in WalkDir::new(dir).min_depth(1).sort_by_file_name() {
+        let entry = entry.unwrap();
+        let name = entry
+            .path()
+            .strip_prefix(dir)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        if entry.file_type().is_dir() {
+            writer.add_directory(name, options).unwrap();
+        } else {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(&fs::read(entry.path()).unwrap()).unwrap();
+        }
+    }
+
+    writer.finish().unwrap().into_inner()
+}
@@ -0,0 +1 @@
+-- Combat Metrics
@@ -0,0 +1 @@
+-- english strings
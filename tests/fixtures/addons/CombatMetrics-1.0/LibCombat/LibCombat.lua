@@ -0,0 +1 @@
+-- LibCombat
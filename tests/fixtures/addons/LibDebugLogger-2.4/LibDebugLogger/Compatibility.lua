@@ -0,0 +1 @@
+-- removed in 2.5.0
@@ -0,0 +1 @@
+-- LibDebugLogger 2.4.1
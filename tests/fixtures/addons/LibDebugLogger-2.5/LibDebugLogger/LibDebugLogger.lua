@@ -0,0 +1 @@
+-- LibDebugLogger 2.5.0
@@ -1,16 +1,26 @@
 extern crate tempfile;
 
+mod common;
+
 use std::error::Error;
 
+use common::EsouiServer;
 use eso_addons::addons::Manager;
+use eso_addons::cache::Cache;
+use eso_addons::errors::Error as AddonError;
 
 #[test]
 fn addon_manager_install_remove_addon() -> Result<(), Box<dyn Error>> {
+    let server = EsouiServer::start();
+    server.publish(2275, "LibDebugLogger", "2.4.1", "LibDebugLogger-2.4");
+
     let addon_dir = tempfile::tempdir()?;
-    let manager = Manager::new(addon_dir.path());
+    let manager = Manager::new(addon_dir.path()).with_client(server.client());
 
-    let addon = manager
-        .download_addon("https://www.esoui.com/downloads/download2275-LibDebugLogger.html")?;
+    let download = manager.download_addon(&server.download_url(2275), None)?;
+    assert_eq!(download.addon.name, "LibDebugLogger");
+    assert_eq!(download.file_id, Some(2275));
+    assert_eq!(download.version.as_deref(), Some("2.4.1"));
 
     let addon_list = manager.get_addons()?;
     assert!(
@@ -19,11 +29,11 @@ fn addon_manager_install_remove_addon() -> Result<(), Box<dyn Error>> {
         addon_list.addons
     );
 
-    manager.delete_addon(&addon)?;
+    manager.delete_addon(&download.addon)?;
 
     let addon_list = manager.get_addons()?;
     assert!(
-        addon_list.addons.len() == 0,
+        addon_list.addons.is_empty(),
         "Installed mods: {:?}",
         addon_list.addons
     );
@@ -33,14 +43,18 @@ fn addon_manager_install_remove_addon() -> Result<(), Box<dyn Error>> {
 
 #[test]
 fn addon_manager_supports_nested_modules() -> Result<(), Box<dyn Error>> {
+    let server = EsouiServer::start();
+    server.publish(1360, "Combat Metrics", "1.0.0", "CombatMetrics-1.0");
+
     let addon_dir = tempfile::tempdir()?;
-    let manager = Manager::new(addon_dir.path());
+    let manager = Manager::new(addon_dir.path()).with_client(server.client());
 
-    manager.download_addon("https://www.esoui.com/downloads/download1360-CombatMetrics")?;
+    let download = manager.download_addon(&server.download_url(1360), None)?;
+    assert_eq!(download.folders, vec!["CombatMetrics", "LibCombat"]);
 
     let addon_list = manager.get_addons()?;
     assert!(
-        addon_list.errors.len() == 0,
+        addon_list.errors.is_empty(),
         "failed to list addons: {:?}",
         addon_list.errors
     );
@@ -52,3 +66,78 @@ fn addon_manager_supports_nested_modules() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn addon_manager_installs_pinned_version() -> Result<(), Box<dyn Error>> {
+    let server = EsouiServer::start();
+    server.publish(2275, "LibDebugLogger", "2.4.1", "LibDebugLogger-2.4");
+    server.publish(2275, "LibDebugLogger", "2.5.0", "LibDebugLogger-2.5");
+
+    let addon_dir = tempfile::tempdir()?;
+    let manager = Manager::new(addon_dir.path()).with_client(server.client());
+
+    let download = manager.download_addon(&server.download_url(2275), Some("2.4.1"))?;
+    assert_eq!(download.version.as_deref(), Some("2.4.1"));
+    assert_eq!(download.addon.manifest.version.as_deref(), Some("2.4.1"));
+
+    // the file history comes from the page fetched for the latest release
+    let page_requests = server
+        .requests()
+        .iter()
+        .filter(|url| url.starts_with("/downloads/") && !url.contains("getfile.php"))
+        .count();
+    assert_eq!(page_requests, 1);
+
+    let download = manager.download_addon(&server.download_url(2275), None)?;
+    assert_eq!(download.addon.manifest.version.as_deref(), Some("2.5.0"));
+    assert_eq!(
+        download.removed_files,
+        vec![std::path::PathBuf::from("LibDebugLogger/Compatibility.lua")]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn addon_manager_rejects_archive_with_wrong_md5() -> Result<(), Box<dyn Error>> {
+    let server = EsouiServer::start();
+    server.publish(2275, "LibDebugLogger", "2.4.1", "LibDebugLogger-2.4");
+    server.publish_md5(2275, "00000000000000000000000000000000");
+
+    let addon_dir = tempfile::tempdir()?;
+    let manager = Manager::new(addon_dir.path()).with_client(server.client());
+
+    let result = manager.download_addon(&server.download_url(2275), None);
+    assert!(
+        matches!(result, Err(AddonError::PublishedChecksumMismatch { .. })),
+        "{:?}",
+        result
+    );
+    assert!(manager.get_addons()?.addons.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn addon_manager_installs_from_cache() -> Result<(), Box<dyn Error>> {
+    let server = EsouiServer::start();
+    server.publish(2275, "LibDebugLogger", "2.4.1", "LibDebugLogger-2.4");
+
+    let addon_dir = tempfile::tempdir()?;
+    let cache_dir = tempfile::tempdir()?;
+    let manager = Manager::new(addon_dir.path())
+        .with_client(server.client())
+        .with_cache(Cache::new(cache_dir.path()));
+
+    manager.download_addon(&server.download_url(2275), None)?;
+    manager.download_addon(&server.download_url(2275), None)?;
+
+    let archive_requests = server
+        .requests()
+        .iter()
+        .filter(|url| url.starts_with("/cdn/"))
+        .count();
+    assert_eq!(archive_requests, 1);
+
+    Ok(())
+}